@@ -6,19 +6,25 @@ pub fn parse<'a>() -> ArgMatches<'a> {
         .author("Nicholas Buckeridge <bucknich@gmail.com>")
         .about([
             "fxtickconv converts tick data to the desired output timeframe formatted to ",
-            "specification. The input and output data columns must/are comma seperated.",
+            "specification. The input and output data columns are comma seperated unless",
+            "other delimiters are given.",
             "Conditions:",
-            " - If there is more than one file then they must be named so that they can be",
-            "      ordered.",
+            " - If there is more than one file then they are ordered by their first tick and",
+            "      must not overlap in time.",
             ""].join("\n").as_str())
         .arg(Arg::with_name("timeframe")
             .index(1)
             .required(true)
             .value_name("TIMEFRAME")
-            .help("Specify the output timeframe (eg. \"1m\" is one minute.)")
-            .long_help("The time frame is specified by a number followed directly by a single \
-            character. The number describes how long the unit in time is and the character is the \
+            .help("Specify the output timeframe (eg. \"1m\" is one minute.) or a list of them")
+            .long_help("The time frame is specified by a number followed directly by a unit. \
+            Several timeframes may be given separated by commas (eg. \"1m,5m,1h,1d\"), the \
+            inputs are then read once and each timeframe is written to its own OUTPUT file. \
+            The number describes how long the unit in time is and the characters are the \
             type of unit. The units are as follows:\n\
+            \tns\tNanoseconds\n\
+            \tus\tMicroseconds\n\
+            \tms\tMilliseconds\n\
             \ts\tSeconds\n\
             \tm\tMinutes\n\
             \th\tHours\n\
@@ -27,11 +33,27 @@ pub fn parse<'a>() -> ArgMatches<'a> {
             \tn\tMonths\n\
             \ty\tYears")
         )
+        .arg(Arg::with_name("offset")
+            .long("offset")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("OFFSET")
+            .help("Shift the bar boundaries from the clock (eg. \"1h\" or \"-7h\")")
+            .long_help("Bars are aligned to the clock: hourly bars start on the hour, 4 hour \
+            bars at 00:00, 04:00, 08:00 and so on, daily bars at midnight, weekly bars on Monday \
+            and monthly bars on the first of the month (all UTC). The offset shifts every \
+            boundary by a fixed amount, written like a timeframe with an optional leading '-'. \
+            For example \"--offset 1h\" starts 4 hour bars at 01:00, 05:00, 09:00 and so on.")
+        )
         .arg(Arg::with_name("output")
             .index(2)
             .required(true)
             .value_name("OUTPUT")
-            .help("The file name to export the data to")
+            .help("The file name to export the data to (eg. \"eurusd_{timeframe}.csv\")")
+            .long_help("The file name to export the data to. \"{timeframe}\" in the name is \
+            replaced with the timeframe as it is written in TIMEFRAME, and must be in the name \
+            when there is more than one timeframe (eg. \"eurusd_{timeframe}.csv\" is written \
+            to eurusd_1m.csv, eurusd_5m.csv and so on).")
         )
         .arg(Arg::with_name("inputs")
             .index(3)
@@ -39,6 +61,13 @@ pub fn parse<'a>() -> ArgMatches<'a> {
             .required(true)
             .value_name("INPUTS")
             .help("The input data file/s. All input files must be of the same format")
+            .long_help("The input data file/s. All input files must be of the same format. \
+            Files compressed with gzip, xz or bzip2 are decoded on the fly, and every file \
+            inside a zip archive is read as an input of its own.")
+        )
+        .arg(Arg::with_name("allow-overlap")
+            .long("allow-overlap")
+            .help("Warn instead of exiting when the input files overlap in time.")
         )
         .arg(Arg::with_name("overwrite")
             .long("overwrite")
@@ -52,6 +81,29 @@ pub fn parse<'a>() -> ArgMatches<'a> {
             .conflicts_with("overwrite")
             .help("Exit with error when the output file already exists.")
         )
+        .arg(Arg::with_name("columns")
+            .long("columns")
+            .takes_value(true)
+            .value_name("TEMPLATE")
+            .conflicts_with_all(&["ask-only", "bid-only", "ask-first", "bid-first", "mid", "stats", "volume",
+                "ask-bid-volume", "spread", "tick-count"])
+            .help("The output columns in order (eg. \"time,bid.open,bid.close,ask.high,ticks\")")
+            .long_help([
+                "The output columns in order, separated by commas (eg. ",
+                "\"time,bid.open,bid.close,ask.high,ticks,spread.mean\"). Replaces --ask-only, ",
+                "--bid-only, --ask-first, --bid-first, --mid, --stats, --volume, --ask-bid-volume, ",
+                "--spread and --tick-count, which are shorthands for common templates. Columns:\n",
+                "    time                      the datetime labelling the bar (see --label)\n",
+                "    ask.open, ask.high, ...   ask, bid or mid open, high, low and close\n",
+                "    ask.twap, bid.mean, ...   ask, bid or mid twap, vwap, mean, median and\n",
+                "                              stddev (see --stats)\n",
+                "    volume                    total volume\n",
+                "    ask.volume, bid.volume    ask and bid volume\n",
+                "    spread.min, spread.max,   spread statistics (see --spread)\n",
+                "    spread.mean, spread.close\n",
+                "    ticks                     number of ticks\n"
+            ].join("").as_str())
+        )
         .arg(Arg::with_name("ask-only")
             .long("ask-only")
             .short("a")
@@ -76,6 +128,193 @@ pub fn parse<'a>() -> ArgMatches<'a> {
             .conflicts_with_all(&["ask-only", "ask-first"])
             .help("Place bid columns before the ask columns.")
         )
+        .arg(Arg::with_name("mid")
+            .long("mid")
+            .help("Export mid price data, alone or after the ask and bid columns.")
+            .long_help("Export mid price data, the mean of the ask and bid of each tick before \
+            the ticks are grouped into bars. Alone only the mid columns are written, with \
+            --ask-only, --bid-only, --ask-first or --bid-first the mid columns follow the ask \
+            and bid columns.")
+        )
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .takes_value(true)
+            .value_name("LIST")
+            .use_delimiter(true)
+            .help("Write statistics of the prices after each OHLC group (eg. \"twap,ask:vwap\")")
+            .long_help([
+                "Write statistics of the prices after the OHLC columns of each ask, bid or mid ",
+                "group. A statistic prefixed with a side (eg. \"mid:median\") is only written for ",
+                "that side. Options:\n",
+                "    twap    time-weighted average price, each tick lasts until the next tick\n",
+                "            or the end of the bar\n",
+                "    vwap    volume-weighted average price, needs a volume column in --tick\n",
+                "    mean    arithmetic mean\n",
+                "    median  median\n",
+                "    stddev  population standard deviation\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("label")
+            .long("label")
+            .short("l")
+            .takes_value(true)
+            .possible_values(&["open", "close", "first-tick", "last-tick"])
+            .default_value("first-tick")
+            .help("Select which datetime labels each bar")
+            .long_help([
+                "Select which datetime labels each bar. Options:\n",
+                "    open        the start of the bar's timeframe\n",
+                "    close       the end of the bar's timeframe\n",
+                "    first-tick  the datetime of the first tick in the bar\n",
+                "    last-tick   the datetime of the last tick in the bar\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("volume")
+            .long("volume")
+            .help("Write the volume of each bar after the prices")
+            .long_help("Write the volume of each bar after the prices, the sum of the 'v' column \
+            of --tick or of the ask and bid volume columns ('A' and 'B') when there is no 'v'.")
+        )
+        .arg(Arg::with_name("ask-bid-volume")
+            .long("ask-bid-volume")
+            .help("Write the ask and bid volumes of each bar after the prices")
+            .long_help("Write the ask and bid volumes of each bar after the prices, the sums of \
+            the 'A' and 'B' columns of --tick.")
+        )
+        .arg(Arg::with_name("spread")
+            .long("spread")
+            .help("Write the min, max, mean and close spread of each bar after the prices")
+            .long_help("Write the minimum, maximum, mean and closing spread (ask - bid) of the \
+            ticks in each bar after the prices and volumes.")
+        )
+        .arg(Arg::with_name("spread-pips")
+            .long("spread-pips")
+            .requires("spread")
+            .help("Write spreads in pips instead of price units")
+            .long_help("Write spreads in pips instead of price units. The pip size is taken from \
+            --pip-size or from the --symbol (0.01 for pairs quoted in JPY or HUF, 0.0001 for \
+            other currency pairs).")
+        )
+        .arg(Arg::with_name("pip-size")
+            .long("pip-size")
+            .takes_value(true)
+            .value_name("SIZE")
+            .requires("spread-pips")
+            .help("The size of a pip for --spread-pips (eg. 0.0001)")
+        )
+        .arg(Arg::with_name("tick-count")
+            .long("tick-count")
+            .help("Write the number of ticks in each bar as the last column")
+        )
+        .arg(Arg::with_name("digits")
+            .long("digits")
+            .takes_value(true)
+            .value_name("N")
+            .help("Write every price with N decimals")
+            .long_help("Write every price with N decimals, rounding halves away from zero. \
+            Without --digits, --symbol or --tick-size prices keep the digits of the input ticks.")
+        )
+        .arg(Arg::with_name("tick-size")
+            .long("tick-size")
+            .takes_value(true)
+            .value_name("SIZE")
+            .help("Round every price to a multiple of the minimum price increment")
+            .long_help("Round every price to the nearest multiple of the minimum price increment \
+            (eg. 0.25 or 0.00005). Prices are written with as many decimals as the tick size \
+            unless --digits or --symbol is given.")
+        )
+        .arg(Arg::with_name("symbol")
+            .long("symbol")
+            .takes_value(true)
+            .value_name("SYMBOL")
+            .help("Write prices with the default precision of the symbol")
+            .long_help("Write prices with the default precision of the symbol (eg. EURUSD or \
+            USD/JPY). Currency pairs are written with 5 decimals, pairs quoted in JPY or HUF \
+            with 3, XAU with 2 and XAG with 3. --digits overrides the default.")
+        )
+        .arg(Arg::with_name("bar-type")
+            .long("bar-type")
+            .takes_value(true)
+            .value_name("TYPE")
+            .default_value("time")
+            .help("Select when bars close, every TIMEFRAME or every number of ticks (eg. \"ticks:500\")")
+            .long_help([
+                "Select when bars close. Options:\n",
+                "    time     at the end of each TIMEFRAME\n",
+                "    ticks:N  after every N ticks, the tick count starts again at the end of ",
+                "each TIMEFRAME (eg. \"1d\" for a count each day). Tick bars open at their first ",
+                "tick, last until the next tick (for --stats twap) and are labelled with their ",
+                "last tick by --label close. They are not filled.\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("fill")
+            .long("fill")
+            .takes_value(true)
+            .possible_values(&["none", "forward", "nan"])
+            .default_value("none")
+            .help("Select how timeframes without ticks are written")
+            .long_help([
+                "Select how timeframes without ticks between the first and last tick are ",
+                "written. Options:\n",
+                "    none     no row is written\n",
+                "    forward  a flat bar at the close of the previous bar, with no volume and ",
+                "no ticks\n",
+                "    nan      a row with NaN prices, no volume and no ticks\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("session")
+            .long("session")
+            .help("Use the trading session calendar, by default FX hours")
+            .long_help("Use the trading session calendar, by default FX hours from Sunday 17:00 \
+            to Friday 17:00 in New York. Days, weeks, months and years start at the open of the \
+            session (17:00 New York), ticks outside of the session are handled by \
+            --out-of-session and --fill does not fill timeframes that start while the market \
+            is closed.")
+        )
+        .arg(Arg::with_name("session-open")
+            .long("session-open")
+            .takes_value(true)
+            .value_name("DAY TIME")
+            .requires("session")
+            .help("The weekly open of the session (default \"Sun 17:00\")")
+        )
+        .arg(Arg::with_name("session-close")
+            .long("session-close")
+            .takes_value(true)
+            .value_name("DAY TIME")
+            .requires("session")
+            .help("The weekly close of the session (default \"Fri 17:00\")")
+        )
+        .arg(Arg::with_name("session-tz")
+            .long("session-tz")
+            .takes_value(true)
+            .value_name("ZONE")
+            .requires("session")
+            .help("The time zone of the session hours (default America/New_York)")
+        )
+        .arg(Arg::with_name("holidays")
+            .long("holidays")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("session")
+            .help("A file of dates (eg. 2016-12-26) the session is closed on")
+            .long_help("A file of dates (eg. 2016-12-26), one on each line, the session is \
+            closed on. A trading day ends at the session close time, so a holiday is closed \
+            from the close time on the day before to the close time on the holiday.")
+        )
+        .arg(Arg::with_name("out-of-session")
+            .long("out-of-session")
+            .takes_value(true)
+            .possible_values(&["exclude", "warn"])
+            .default_value("exclude")
+            .help("Select what is done with ticks outside of the --session")
+            .long_help([
+                "Select what is done with ticks outside of the --session. Options:\n",
+                "    exclude  the ticks are dropped\n",
+                "    warn     the ticks are kept, a warning gives their count and the first of ",
+                "them (the bars holding them are not marked)\n"
+            ].join("").as_str())
+        )
         .arg(Arg::with_name("headers")
             .long("headers")
             .short("h")
@@ -87,18 +326,144 @@ pub fn parse<'a>() -> ArgMatches<'a> {
             .takes_value(true)
             .default_value("xdab")
             .required(true)
-            .help("Informs the converter that the input data is tick data")
+            .help("The columns of the input tick data")
             .long_help([
-                "Informs the converter that the input data is tick data. When this option ",
-                "is used, the format of the data must be specified. Specifically the date, ",
-                "ask and bid columns. Format options:\n",
+                "The columns of the input tick data, which must have the date, ask and bid ",
+                "columns. Not used with --bars. Format options:\n",
                 "    Option  Description\n",
                 "    d       datetime (must have one)\n",
                 "    a       ask (must have one)\n",
                 "    b       bid (must have one)\n",
+                "    v       volume\n",
+                "    A       ask volume\n",
+                "    B       bid volume\n",
                 "    x       column filler\n"
             ].join("").as_str())
         )
+        .arg(Arg::with_name("bars")
+            .long("bars")
+            .takes_value(true)
+            .value_name("TEMPLATE")
+            .conflicts_with_all(&["datetime-col", "ask-col", "bid-col"])
+            .help("Read OHLC bars with the columns of the template instead of ticks")
+            .long_help([
+                "Read bars instead of ticks, with the columns of the template (see --columns). ",
+                "The bars are resampled to the timeframe: the first open, highest high, lowest ",
+                "low and last close, and the sum of the volumes and tick counts. Bars are ",
+                "grouped by their datetime, so they must be labelled with their open (eg. ",
+                "written by fxconv with --label open). Statistics and spreads cannot be ",
+                "resampled and mid prices need mid columns. Eg. the default output of fxconv:\n",
+                "    --bars time,ask.open,ask.high,ask.low,ask.close,bid.open,bid.high,bid.low,bid.close\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("datetime-format")
+            .long("datetime-format")
+            .takes_value(true)
+            .value_name("FORMAT")
+            .help("Pattern of the input datetime column (eg. \"%Y.%m.%d %H:%M:%S%.f\")")
+            .long_help([
+                "Pattern of the input datetime column, by default datetimes are read as ",
+                "\"20161101 22:30:03.617\". The pattern is strftime style, common specifiers:\n",
+                "    %Y      year (2016)\n",
+                "    %m      month (11)\n",
+                "    %d      day (01)\n",
+                "    %H      hour (22)\n",
+                "    %M      minute (30)\n",
+                "    %S      second (03)\n",
+                "    %.f     fractional seconds, if any (.617)\n",
+                "    %z      offset from UTC (+0200), the datetime is converted to UTC\n",
+                "Examples: \"%Y-%m-%dT%H:%M:%S%.fZ\", \"%d/%m/%Y %H:%M:%S\"\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("epoch")
+            .long("epoch")
+            .takes_value(true)
+            .value_name("UNIT")
+            .possible_values(&["auto", "s", "ms", "us", "ns"])
+            .conflicts_with("datetime-format")
+            .help("The input datetime column is time since the unix epoch")
+            .long_help([
+                "The input datetime column is time since the unix epoch (UTC), as a whole or ",
+                "fractional number of the unit. Units:\n",
+                "    auto    detected from the number of digits\n",
+                "    s       seconds (1478039403.617)\n",
+                "    ms      milliseconds (1478039403617)\n",
+                "    us      microseconds (1478039403617000)\n",
+                "    ns      nanoseconds (1478039403617000000)\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("input-tz")
+            .long("input-tz")
+            .takes_value(true)
+            .value_name("ZONE")
+            .help("Time zone of the input datetimes (default UTC)")
+            .long_help([
+                "Time zone of the input datetimes (default UTC). Datetimes with an offset (%z) ",
+                "or from the unix epoch are not affected. Zones are written as:\n",
+                "    UTC                      coordinated universal time\n",
+                "    America/New_York         a tz database zone, with daylight saving\n",
+                "    +02:00                   a fixed offset from UTC\n",
+                "    America/New_York+07:00   a zone shifted by a fixed amount, eg. the \"New York\n",
+                "                             close\" zone that is GMT+2 in winter and GMT+3 in summer\n"
+            ].join("").as_str())
+        )
+        .arg(Arg::with_name("output-tz")
+            .long("output-tz")
+            .takes_value(true)
+            .value_name("ZONE")
+            .help("Time zone of the output datetimes and bar boundaries (default UTC)")
+            .long_help("Time zone of the output datetimes (default UTC), written in the same way \
+            as --input-tz. Bars are aligned to the clock of this zone, so daily bars start at its \
+            midnight whatever its daylight saving.")
+        )
+        .arg(Arg::with_name("in-delim")
+            .long("in-delim")
+            .takes_value(true)
+            .value_name("DELIMITER")
+            .default_value(",")
+            .help("Column delimiter of the input data")
+            .long_help("Column delimiter of the input data. Any single character (eg. \";\" or \
+            \"|\"), \"tab\", \"space\" or \"whitespace\" which splits on any run of spaces and \
+            tabs (datetimes must then not contain spaces).")
+        )
+        .arg(Arg::with_name("out-delim")
+            .long("out-delim")
+            .takes_value(true)
+            .value_name("DELIMITER")
+            .default_value(",")
+            .help("Column delimiter of the output data, as --in-delim")
+        )
+        .arg(Arg::with_name("input-header")
+            .long("input-header")
+            .help("The first line of every input is a header")
+            .long_help("The first line of every input is a header and is skipped. Without this \
+            option a first line that holds no numbers is taken to be a header.")
+        )
+        .arg(Arg::with_name("datetime-col")
+            .long("datetime-col")
+            .takes_value(true)
+            .value_name("NAME")
+            .requires_all(&["ask-col", "bid-col"])
+            .help("Read the datetime from the column with this header name")
+            .long_help("Read the datetime from the column with this header name (case \
+            insensitive). Must be given with --ask-col and --bid-col, the columns are then found \
+            in the header of each input instead of by --tick, so their order may differ between \
+            inputs. Other columns are skipped.")
+        )
+        .arg(Arg::with_name("ask-col")
+            .long("ask-col")
+            .takes_value(true)
+            .value_name("NAME")
+            .requires_all(&["datetime-col", "bid-col"])
+            .help("Read the ask from the column with this header name")
+        )
+        .arg(Arg::with_name("bid-col")
+            .long("bid-col")
+            .takes_value(true)
+            .value_name("NAME")
+            .requires_all(&["datetime-col", "ask-col"])
+            .help("Read the bid from the column with this header name")
+        )
         .arg(Arg::with_name("bar")
             .long("bar")
             .help("Show progress bar")
@@ -1,81 +1,418 @@
 use chrono::prelude::*;
-use fxconv::AskBidOption;
+use rust_decimal::Decimal;
 use std::thread;
 use std::sync::mpsc::channel;
 use fxconv::AskBid;
+use fxconv::Label;
+use fxconv::ExtraColumn;
+use fxconv::Statistic;
+use fxconv::BarType;
+use fxconv::Fill;
+use formatter::Volume;
+use formatter::Bar;
+use formatter::Ohlc;
+use template::{Aggregate, Column};
+use rust_decimal::MathematicalOps;
 use grouper::TickGroup;
+use market::instrument::Precision;
 use std::sync::mpsc::{Receiver};
 
+/// The decimals kept of averages, which rarely divide exactly
+const MEAN_DECIMALS: u32 = 10;
+
 pub struct Row {
     pub datetime: DateTime<Utc>,
-    pub column_data: Vec<f32>
+    /// Prices keep the digits of the input ticks, rows filled with --fill nan
+    /// have no prices
+    pub column_data: Vec<Option<Decimal>>
 }
 
 // Return the first value of the vector
-fn open(column: & Vec<f32>) -> f32 {
+fn open(column: & Vec<Decimal>) -> Decimal {
     column.iter().next().unwrap().clone()
 }
 
 // Return the highest value of the vector
-fn high(column: & Vec<f32>) -> f32 {
-    column.iter().max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap().clone()
+fn high(column: & Vec<Decimal>) -> Decimal {
+    column.iter().max().unwrap().clone()
 }
 
 //  Return the lowest value fo the vector
-fn low(column: & Vec<f32>) -> f32 {
-    column.iter().min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap().clone()
+fn low(column: & Vec<Decimal>) -> Decimal {
+    column.iter().min().unwrap().clone()
 }
 
 // Return the last value of the vector
-fn close(column: & Vec<f32>) -> f32 {
+fn close(column: & Vec<Decimal>) -> Decimal {
     column.iter().rev().next().unwrap().clone()
 }
 
-// Create an output row from the input data
-fn process(column_structure: &[AskBid], rows_ask: & Vec<f32>, rows_bid: & Vec<f32>) -> Vec<f32> {
-    let mut row: Vec<f32> = Vec::new();
-    for group in column_structure {
-        match *group {
-            AskBid::Ask => {
-                row.push(open(rows_ask));
-                row.push(high(rows_ask));
-                row.push(low(rows_ask));
-                row.push(close(rows_ask));
-            },
-            AskBid::Bid => {
-                row.push(open(rows_bid));
-                row.push(high(rows_bid));
-                row.push(low(rows_bid));
-                row.push(close(rows_bid));
-            }
-        };
+// Return the average value of the vector
+fn mean(column: &[Decimal]) -> Decimal {
+    let sum: Decimal = column.iter().sum();
+    (sum / Decimal::from(column.len())).round_dp(MEAN_DECIMALS).normalize()
+}
+
+// Return the middle value of the vector, or the mean of the two middle values
+fn median(column: &[Decimal]) -> Decimal {
+    let mut sorted = column.to_vec();
+    sorted.sort();
+    let middle = sorted.len() / 2;
+    match sorted.len() % 2 {
+        0 => ((sorted[middle - 1] + sorted[middle]) / Decimal::TWO).normalize(),
+        _ => sorted[middle]
     }
-    row
 }
 
-// Create the converter
-pub fn create(rx_grouper: Receiver<Option<TickGroup>>, ask_bid: Option<AskBidOption>)  -> (thread::JoinHandle<()>, Receiver<Option<Row>>) {
-    // Build the conversion structure
-    let column_structure: &[AskBid] = match ask_bid {
-        Some(AskBidOption::AskOnly) => &[AskBid::Ask],
-        Some(AskBidOption::BidOnly) => &[AskBid::Bid],
-        Some(AskBidOption::BidFirst) => &[AskBid::Bid, AskBid::Ask],
-        _ => &[AskBid::Ask, AskBid::Bid]
+// Return the population standard deviation of the vector
+fn std_dev(column: &[Decimal]) -> Decimal {
+    let sum: Decimal = column.iter().sum();
+    let mean = sum / Decimal::from(column.len());
+    let squares: Decimal = column.iter().map(|value| (value - mean) * (value - mean)).sum();
+    let variance = squares / Decimal::from(column.len());
+    variance.sqrt().unwrap().round_dp(MEAN_DECIMALS).normalize()
+}
+
+// Return the average of the vector weighted by the weight of each value, the
+// plain average when there is no weight
+fn weighted_mean(column: &[Decimal], weights: &[Decimal]) -> Decimal {
+    let total: Decimal = weights.iter().sum();
+    if total.is_zero() {
+        return mean(column);
+    }
+    let sum: Decimal = column.iter().zip(weights.iter()).map(|(value, weight)| value * weight).sum();
+    (sum / total).round_dp(MEAN_DECIMALS).normalize()
+}
+
+// Return how long each tick of the group lasts, until the next tick or the
+// close of the bar for the last tick, in nanoseconds
+fn durations(group: &TickGroup) -> Vec<Decimal> {
+    let mut ends: Vec<DateTime<Utc>> = group.datetimes.iter().skip(1).cloned().collect();
+    ends.push(group.end);
+    group.datetimes.iter().zip(ends.iter()).map(|(start, end)| {
+        let duration = end.signed_duration_since(*start);
+        Decimal::from(duration.num_nanoseconds().unwrap_or(i64::MAX))
+    }).collect()
+}
+
+// Return the open, high or low of the bars of a side, or the close of the last bar
+fn resample(bars: &[Bar], side: AskBid, aggregate: &Aggregate) -> Decimal {
+    let prices: Vec<Ohlc> = bars.iter().filter_map(|bar| bar.side(side)).collect();
+    match *aggregate {
+        Aggregate::Open => prices.first().unwrap().open,
+        Aggregate::High => prices.iter().map(|ohlc| ohlc.high).max().unwrap(),
+        Aggregate::Low => prices.iter().map(|ohlc| ohlc.low).min().unwrap(),
+        Aggregate::Close => prices.last().unwrap().close,
+        Aggregate::Statistic(_) => panic!("Statistics cannot be resampled from bars")
+    }
+}
+
+// Return the statistic of the prices of the group
+fn statistic(statistic: &Statistic, column: &[Decimal], group: &TickGroup) -> Decimal {
+    match *statistic {
+        Statistic::Twap => weighted_mean(column, &durations(group)),
+        Statistic::Vwap => weighted_mean(column, &group.volumes),
+        Statistic::Mean => mean(column),
+        Statistic::Median => median(column),
+        Statistic::StdDev => std_dev(column)
+    }
+}
+
+// Return the spread (ask - bid) of each tick, in pips when there is a pip size
+fn spreads(group: &TickGroup, pip_size: Option<Decimal>) -> Vec<Decimal> {
+    group.asks.iter().zip(group.bids.iter()).map(|(ask, bid)| match pip_size {
+        Some(pip_size) => ((ask - bid) / pip_size).normalize(),
+        None => ask - bid
+    }).collect()
+}
+
+// Return the mid price of each tick, computed before the ticks are aggregated
+// as the high and low of the mid cannot be found from the ask and bid bars
+fn mids(rows_ask: &[Decimal], rows_bid: &[Decimal]) -> Vec<Decimal> {
+    rows_ask.iter().zip(rows_bid.iter()).map(|(ask, bid)| {
+        // the mid has the digits of the quotes, and one more when it falls between them
+        let scale = ask.scale().max(bid.scale());
+        let mut mid = ((ask + bid) / Decimal::TWO).round_dp(scale + 1);
+        if mid.round_dp(scale) == mid {
+            mid.rescale(scale);
+        }
+        mid
+    }).collect()
+}
+
+// Create an output row from the input data, a value for every column of the
+// template except the datetime. Prices and spreads in price units are rounded
+// like the prices, spreads in pips, volumes and tick counts are not.
+fn process(columns: &[Column], group: &TickGroup, precision: Option<Precision>, pip_size: Option<Decimal>) -> Vec<Decimal> {
+    let rows_mid = if columns.iter().any(|column| matches!(*column, Column::Price(AskBid::Mid, _))) {
+        mids(&group.asks, &group.bids)
+    } else {
+        Vec::new()
+    };
+    let is_spread = |column: &Column| match *column {
+        Column::Extra(extra) => [ExtraColumn::SpreadMin, ExtraColumn::SpreadMax, ExtraColumn::SpreadMean, ExtraColumn::SpreadClose].contains(&extra),
+        _ => false
+    };
+    let rows_spread = if columns.iter().any(is_spread) { spreads(group, pip_size) } else { Vec::new() };
+    let price = |value: Decimal| match precision {
+        Some(precision) => precision.round(value),
+        None => value
+    };
+    let spread = |value: Decimal| match pip_size {
+        Some(_) => value,
+        None => price(value)
     };
+    columns.iter().filter_map(|column| match *column {
+        Column::DateTime => None,
+        Column::Price(side, aggregate) => {
+            if !group.bars.is_empty() {
+                return Some(price(resample(&group.bars, side, &aggregate)));
+            }
+            let rows = match side {
+                AskBid::Ask => &group.asks,
+                AskBid::Bid => &group.bids,
+                AskBid::Mid => &rows_mid
+            };
+            Some(price(match aggregate {
+                Aggregate::Open => open(rows),
+                Aggregate::High => high(rows),
+                Aggregate::Low => low(rows),
+                Aggregate::Close => close(rows),
+                Aggregate::Statistic(ref stat) => statistic(stat, rows, group)
+            }))
+        },
+        Column::Extra(extra) => Some(match extra {
+            ExtraColumn::Volume => group.volume.total,
+            ExtraColumn::AskVolume => group.volume.ask,
+            ExtraColumn::BidVolume => group.volume.bid,
+            ExtraColumn::SpreadMin => spread(low(&rows_spread)),
+            ExtraColumn::SpreadMax => spread(high(&rows_spread)),
+            ExtraColumn::SpreadMean => spread(mean(&rows_spread)),
+            ExtraColumn::SpreadClose => spread(close(&rows_spread)),
+            ExtraColumn::TickCount if !group.bars.is_empty() => group.bars.iter().filter_map(|bar| bar.ticks).sum(),
+            ExtraColumn::TickCount => Decimal::from(group.datetimes.len())
+        })
+    }).collect()
+}
+
+// Create the row of a timeframe without ticks, the volumes and tick count are
+// zero and there are no prices
+fn empty(columns: &[Column]) -> Vec<Option<Decimal>> {
+    columns.iter().filter_map(|column| match *column {
+        Column::DateTime => None,
+        Column::Extra(ExtraColumn::Volume) | Column::Extra(ExtraColumn::AskVolume) |
+        Column::Extra(ExtraColumn::BidVolume) | Column::Extra(ExtraColumn::TickCount) => Some(Some(Decimal::ZERO)),
+        _ => Some(None)
+    }).collect()
+}
 
+// Return a group for a timeframe without ticks that holds the closing prices
+// of the previous bar, so that it is written as a flat bar without ticks
+fn flat(group: &TickGroup, previous: &TickGroup) -> TickGroup {
+    let bars = previous.bars.last().map(|bar| Bar {
+        ask: bar.ask.map(|ohlc| Ohlc::flat(ohlc.close)),
+        bid: bar.bid.map(|ohlc| Ohlc::flat(ohlc.close)),
+        mid: bar.mid.map(|ohlc| Ohlc::flat(ohlc.close)),
+        ticks: Some(Decimal::ZERO)
+    });
+    TickGroup {
+        start: group.start,
+        end: group.end,
+        datetimes: Vec::new(),
+        asks: vec![close(&previous.asks)],
+        bids: vec![close(&previous.bids)],
+        volumes: vec![Decimal::ZERO],
+        volume: Volume::default(),
+        bars: bars.into_iter().collect()
+    }
+}
+
+// Return the datetime the output row is labeled with, bars without ticks are
+// labeled with their open
+fn label(group: &TickGroup, label: &Label) -> DateTime<Utc> {
+    match *label {
+        Label::Open => group.start,
+        Label::Close => group.end,
+        Label::FirstTick => *group.datetimes.first().unwrap_or(&group.start),
+        Label::LastTick => *group.datetimes.last().unwrap_or(&group.start)
+    }
+}
+
+// Create the converter
+pub fn create(rx_grouper: Receiver<Option<TickGroup>>, columns: Vec<Column>, bar_label: Label, bar_type: BarType, precision: Option<Precision>, pip_size: Option<Decimal>, fill: Fill)  -> (thread::JoinHandle<()>, Receiver<Option<Row>>) {
     let (tx_converter, rx_converter) = channel();
+    // tick bars last until the next tick, --label close labels them with their last tick
+    let bar_label = match (bar_label, bar_type) {
+        (Label::Close, BarType::Ticks(_)) => Label::LastTick,
+        (bar_label, _) => bar_label
+    };
     let converter_thread = thread::Builder::new().name("converter".to_string()).spawn(move || {
 
+        // the last group with ticks, the prices of filled bars
+        let mut previous: Option<TickGroup> = None;
         while let Some(group) = rx_grouper.recv().expect("Unable to receive from channel") {
 
+            let column_data = match (group.datetimes.is_empty(), fill, &previous) {
+                (true, Fill::Forward, Some(previous)) => process(&columns, &flat(&group, previous), precision, pip_size).into_iter().map(Some).collect(),
+                (true, _, _) => empty(&columns),
+                (false, _, _) => process(&columns, &group, precision, pip_size).into_iter().map(Some).collect()
+            };
             let row = Row {
-                datetime: *group.datetimes.iter().next().unwrap(),
-                column_data: process(column_structure, & group.asks, & group.bids)
+                datetime: label(&group, &bar_label),
+                column_data
             };
             tx_converter.send(Some(row)).unwrap();
+            if !group.datetimes.is_empty() {
+                previous = Some(group);
+            }
         }
         tx_converter.send(None).unwrap();
     });
 
     (converter_thread.expect("Thread did not spawn correctly"), rx_converter)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use template;
+
+    fn gen_group() -> TickGroup {
+        TickGroup {
+            datetimes: vec![Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617), Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 45, 102)],
+            asks: vec![dec!(0.76541), dec!(0.76558)],
+            bids: vec![dec!(0.76551), dec!(0.76559)],
+            volumes: vec![dec!(0), dec!(0)],
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(22, 0, 0), Utc.ymd(2016, 11, 1).and_hms(23, 0, 0))
+        }
+    }
+
+    #[test]
+    fn labels() {
+        let group = gen_group();
+        assert_eq!(label(&group, &Label::Open), Utc.ymd(2016, 11, 1).and_hms(22, 0, 0));
+        assert_eq!(label(&group, &Label::Close), Utc.ymd(2016, 11, 1).and_hms(23, 0, 0));
+        assert_eq!(label(&group, &Label::FirstTick), Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617));
+        assert_eq!(label(&group, &Label::LastTick), Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 45, 102));
+    }
+
+    #[test]
+    fn volume_and_tick_count() {
+        let mut group = gen_group();
+        group.volume = Volume { total: dec!(3.5), ask: dec!(1.25), bid: dec!(2.25) };
+        let columns = template::parse("ticks,volume,ask.volume,bid.volume").unwrap();
+        assert_eq!(process(&columns, &group, None, None), vec![dec!(2), dec!(3.5), dec!(1.25), dec!(2.25)]);
+        assert_eq!(process(&[Column::DateTime], &group, None, None), vec![]);
+    }
+
+    #[test]
+    fn spread_statistics() {
+        let mut group = gen_group();
+        group.asks = vec![dec!(0.76551), dec!(0.76558), dec!(0.76570)];
+        group.bids = vec![dec!(0.76541), dec!(0.76550), dec!(0.76557)];
+        let columns = template::parse("spread.min,spread.max,spread.mean,spread.close").unwrap();
+        assert_eq!(process(&columns, &group, None, None), vec![dec!(0.00008), dec!(0.00013), dec!(0.0001033333), dec!(0.00013)]);
+        let rounded: Vec<String> = process(&columns, &group, Some(Precision::new(5)), None).iter().map(|v| v.to_string()).collect();
+        assert_eq!(rounded, vec!["0.00008", "0.00013", "0.00010", "0.00013"]);
+        let pips: Vec<String> = process(&columns, &group, Some(Precision::new(5)), Some(dec!(0.0001))).iter().map(|v| v.to_string()).collect();
+        assert_eq!(pips, vec!["0.8", "1.3", "1.0333333333", "1.3"]);
+    }
+
+    #[test]
+    fn mid_prices() {
+        let asks = vec![dec!(0.76551), dec!(0.76562), dec!(0.76545)];
+        let bids = vec![dec!(0.76541), dec!(0.76549), dec!(0.76544)];
+        assert_eq!(mids(&asks, &bids), vec![dec!(0.76546), dec!(0.765555), dec!(0.765445)]);
+        let mut group = gen_group();
+        group.asks = asks;
+        group.bids = bids;
+        let columns = template::shorthand(&[AskBid::Mid], &[], &[]);
+        assert_eq!(process(&columns, &group, None, None), vec![dec!(0.76546), dec!(0.765555), dec!(0.765445), dec!(0.765445)]);
+        let columns = template::shorthand(&[AskBid::Bid, AskBid::Mid], &[], &[]);
+        assert_eq!(process(&columns, &group, None, None).len(), 8);
+        let digits: Vec<String> = mids(&[dec!(0.76540), dec!(0.76558)], &[dec!(0.76540), dec!(0.76559)]).iter().map(|v| v.to_string()).collect();
+        assert_eq!(digits, vec!["0.76540", "0.765585"]);
+    }
+
+    #[test]
+    fn statistics() {
+        let mut group = gen_group();
+        // ticks last 10s, 30s and 20s until the close of the bar
+        group.start = Utc.ymd(2016, 11, 1).and_hms(22, 30, 0);
+        group.end = Utc.ymd(2016, 11, 1).and_hms(22, 31, 0);
+        group.datetimes = vec![Utc.ymd(2016, 11, 1).and_hms(22, 30, 0), Utc.ymd(2016, 11, 1).and_hms(22, 30, 10), Utc.ymd(2016, 11, 1).and_hms(22, 30, 40)];
+        group.asks = vec![dec!(1.1), dec!(1.4), dec!(1.2)];
+        group.bids = vec![dec!(1.0), dec!(1.3), dec!(1.1)];
+        group.volumes = vec![dec!(2), dec!(1), dec!(1)];
+        let stat = |s: Statistic, group: &TickGroup| statistic(&s, &group.asks, group);
+        assert_eq!(stat(Statistic::Twap, &group), dec!(1.2833333333));
+        assert_eq!(stat(Statistic::Vwap, &group), dec!(1.2));
+        assert_eq!(stat(Statistic::Mean, &group), dec!(1.2333333333));
+        assert_eq!(stat(Statistic::Median, &group), dec!(1.2));
+        assert_eq!(stat(Statistic::StdDev, &group), dec!(0.1247219129));
+        assert_eq!(median(&[dec!(1.1), dec!(1.4)]), dec!(1.25));
+        // without any volume the plain mean is used
+        group.volumes = vec![dec!(0), dec!(0), dec!(0)];
+        assert_eq!(stat(Statistic::Vwap, &group), dec!(1.2333333333));
+        // statistics follow the OHLC of their side
+        let columns = template::shorthand(&[AskBid::Ask, AskBid::Bid], &[(AskBid::Bid, Statistic::Median), (AskBid::Ask, Statistic::Mean)], &[]);
+        let row = process(&columns, &group, None, None);
+        assert_eq!(row, vec![dec!(1.1), dec!(1.4), dec!(1.1), dec!(1.2), dec!(1.2333333333), dec!(1.0), dec!(1.3), dec!(1.0), dec!(1.1), dec!(1.1)]);
+    }
+
+    #[test]
+    fn template_order() {
+        let group = gen_group();
+        let columns = template::parse("bid.close,time,ask.open,ticks,ask.high").unwrap();
+        assert_eq!(process(&columns, &group, None, None), vec![dec!(0.76559), dec!(0.76541), dec!(2), dec!(0.76558)]);
+        let rounded: Vec<String> = process(&columns, &group, Some(Precision::new(6)), None).iter().map(|v| v.to_string()).collect();
+        assert_eq!(rounded, vec!["0.765590", "0.765410", "2", "0.765580"]);
+    }
+
+    #[test]
+    fn filled_bars() {
+        let previous = gen_group();
+        let group = TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(23, 0, 0), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0));
+        let columns = template::parse("time,ask.open,ask.high,bid.low,mid.close,ask.stddev,ask.twap,spread.max,volume,ticks").unwrap();
+        let row = process(&columns, &flat(&group, &previous), None, None);
+        assert_eq!(row, vec![dec!(0.76558), dec!(0.76558), dec!(0.76559), dec!(0.765585), dec!(0), dec!(0.76558), dec!(-0.00001), dec!(0), dec!(0)]);
+        assert_eq!(empty(&columns), vec![None, None, None, None, None, None, None, Some(dec!(0)), Some(dec!(0))]);
+        assert_eq!(label(&group, &Label::FirstTick), Utc.ymd(2016, 11, 1).and_hms(23, 0, 0));
+    }
+
+    #[test]
+    fn tick_bar_twap() {
+        // a full tick bar lasts until the tick after its last
+        let group = TickGroup {
+            datetimes: vec![Utc.ymd(2016, 11, 1).and_hms(22, 30, 0), Utc.ymd(2016, 11, 1).and_hms(22, 30, 1)],
+            asks: vec![dec!(1.0), dec!(2.0)],
+            bids: vec![dec!(1.0), dec!(2.0)],
+            volumes: vec![dec!(0), dec!(0)],
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(22, 30, 0), Utc.ymd(2016, 11, 1).and_hms(22, 30, 3))
+        };
+        let columns = template::parse("time,ask.open,ask.twap").unwrap();
+        assert_eq!(process(&columns, &group, None, None), vec![dec!(1.0), dec!(1.6666666667)]);
+    }
+
+    #[test]
+    fn resampled_bars() {
+        let bar = |open, high, low, close, ticks| Bar {
+            ask: None,
+            bid: Some(Ohlc { open, high, low, close }),
+            mid: None,
+            ticks: Some(ticks)
+        };
+        let group = TickGroup {
+            bars: vec![
+                bar(dec!(0.76551), dec!(0.76572), dec!(0.76541), dec!(0.76566), dec!(4)),
+                bar(dec!(0.76566), dec!(0.76580), dec!(0.76559), dec!(0.76570), dec!(3)),
+                bar(dec!(0.76570), dec!(0.76571), dec!(0.76530), dec!(0.76535), dec!(2))
+            ],
+            ..gen_group()
+        };
+        let columns = template::parse("time,bid.open,bid.high,bid.low,bid.close,ticks").unwrap();
+        assert_eq!(process(&columns, &group, None, None), vec![dec!(0.76551), dec!(0.76580), dec!(0.76530), dec!(0.76535), dec!(9)]);
+        let empty = TickGroup { datetimes: Vec::new(), ..gen_group() };
+        assert_eq!(process(&columns, &flat(&empty, &group), None, None), vec![dec!(0.76535), dec!(0.76535), dec!(0.76535), dec!(0.76535), dec!(0)]);
+    }
+}
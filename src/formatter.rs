@@ -3,6 +3,12 @@ use std::str::FromStr;
 use std::fmt::Display;
 use std::thread;
 use chrono::prelude::*;
+use rust_decimal::Decimal;
+use market::timezone::Zone;
+use fxconv::AskBid;
+use fxconv::Delimiter;
+use fxconv::ExtraColumn;
+use template::{Aggregate, Column};
 
 #[derive(Debug)]
 #[derive(PartialEq)]
@@ -11,92 +17,451 @@ pub enum TickDescription {
     DateTime,
     Ask,
     Bid,
+    Volume,
+    AskVolume,
+    BidVolume,
     Filler
 }
 
+/// How the datetime column is written
 #[derive(Debug)]
 #[derive(PartialEq)]
+#[derive(Clone)]
+pub enum DateTimeFormat {
+    /// "20161101 22:30:03.617"
+    Fixed,
+    /// A strftime style pattern, eg. "%Y.%m.%d %H:%M:%S%.f"
+    Pattern(String),
+    /// Time since the unix epoch, eg. "1478039403617" or "1478039403.617"
+    Epoch(EpochUnit)
+}
+
+/// The unit of an epoch timestamp
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum EpochUnit {
+    /// Detected from the number of digits
+    Auto,
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds
+}
+
+/// Whether the inputs start with a header line naming the columns
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum Header {
+    /// A first line without any numbers is a header
+    Auto,
+    Present
+}
+
+/// The header names of the columns to read, used instead of the positional
+/// tick description so that the column order may change between inputs
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub struct ColumnNames {
+    pub datetime: String,
+    pub ask: String,
+    pub bid: String
+}
+
+/// Describes how the lines of tick data are read
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct TickFormat {
+    pub tick: Vec<TickDescription>,
+    pub datetime: DateTimeFormat,
+    /// The zone of datetimes written without an offset
+    pub zone: Zone,
+    pub delimiter: Delimiter,
+    pub header: Header,
+    pub columns: Option<ColumnNames>,
+    /// The columns of input bars, read instead of ticks with --bars
+    pub bars: Option<Vec<Column>>
+}
+
+impl TickFormat {
+    /// Whether the first line of an input is a header rather than tick data.
+    /// Detected headers have a column for every tick description and no numbers.
+    /// Bar headers are any lines without numbers.
+    pub fn is_header(&self, line: &str) -> bool {
+        let cols = self.delimiter.split(line);
+        if self.bars.is_some() {
+            return cols.iter().all(|col| col.trim().parse::<f64>().is_err());
+        }
+        if self.header == Header::Present || self.columns.is_some() {
+            return true;
+        }
+        cols.len() == self.tick.len() && cols.iter().all(|col| !col.trim().is_empty() && col.trim().parse::<f64>().is_err())
+    }
+
+    /// The most header lines an input starts with, bars written by fxconv with
+    /// --headers have two
+    pub fn header_lines(&self) -> usize {
+        match self.bars {
+            Some(_) => 2,
+            None => 1
+        }
+    }
+
+    /// The format of the input with the given header line, named columns are
+    /// found in the header and every other column is skipped
+    pub fn with_header(&self, line: &str) -> Result<TickFormat, String> {
+        let names = match self.columns {
+            Some(ref names) => names,
+            None => return Ok(self.clone())
+        };
+        let header: Vec<String> = self.delimiter.split(line).iter()
+            .map(|col| col.trim().trim_matches('"').to_lowercase())
+            .collect();
+        let mut tick = vec![TickDescription::Filler; header.len()];
+        let named = vec![(&names.datetime, TickDescription::DateTime), (&names.ask, TickDescription::Ask), (&names.bid, TickDescription::Bid)];
+        for (name, desc) in named {
+            match header.iter().position(|col| *col == name.to_lowercase()) {
+                Some(index) => tick[index] = desc,
+                None => return Err(format!("column '{}' not found in header '{}'", name, line))
+            }
+        }
+        Ok(TickFormat { tick, ..self.clone() })
+    }
+}
+
+/// The volume traded with a tick, zero when the input has no volume columns
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy, Default)]
+pub struct Volume {
+    pub total: Decimal,
+    pub ask: Decimal,
+    pub bid: Decimal
+}
+
+/// The open, high, low and close prices of a bar
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub struct Ohlc {
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal
+}
+
+impl Ohlc {
+    /// A bar that opens, stays and closes at the price
+    pub fn flat(price: Decimal) -> Ohlc {
+        Ohlc { open: price, high: price, low: price, close: price }
+    }
+}
+
+/// The prices of an input bar, for each side in the --bars columns
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub struct Bar {
+    pub ask: Option<Ohlc>,
+    pub bid: Option<Ohlc>,
+    pub mid: Option<Ohlc>,
+    /// The number of ticks in the bar
+    pub ticks: Option<Decimal>
+}
+
+impl Bar {
+    pub fn side(&self, side: AskBid) -> Option<Ohlc> {
+        match side {
+            AskBid::Ask => self.ask,
+            AskBid::Bid => self.bid,
+            AskBid::Mid => self.mid
+        }
+    }
+}
+
+/// A tick, or a bar with the closing prices as its ask and bid
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
 pub struct InputRow {
     pub datetime: DateTime<Utc>,
-    pub ask: f32,
-    pub bid: f32
+    pub ask: Decimal,
+    pub bid: Decimal,
+    pub volume: Volume,
+    /// Boxed so that ticks, which have no bar, stay small
+    pub bar: Option<Box<Bar>>
+}
+
+impl InputRow {
+    /// A tick
+    pub fn new(datetime: DateTime<Utc>, ask: Decimal, bid: Decimal, volume: Volume) -> InputRow {
+        InputRow { datetime, ask, bid, volume, bar: None }
+    }
 }
 
 /// From the input lines, generates tick data
-pub fn create(rx_producer: Receiver<Option<(usize, String)>>, tick: Vec<TickDescription>) -> (thread::JoinHandle<()>, Receiver<Option<InputRow>>) {
+pub fn create(rx_producer: Receiver<Option<(usize, String)>>, format: TickFormat) -> (thread::JoinHandle<()>, Receiver<Option<InputRow>>) {
     let (tx_ticks, rx_ticks) = channel();
     let t = thread::Builder::new().name("formatter".to_string()).spawn(move || {
-        formatter(tx_ticks, rx_producer, tick);
+        formatter(tx_ticks, rx_producer, format);
     });
     (t.expect("Thread did not spawn correctly"), rx_ticks)
 }
 
+/// Why a line could not be read as a tick
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub enum ParseError {
+    /// The columns of the line do not match the tick description
+    InvalidLine,
+    /// The data of a column is incorrectly formatted
+    Column(String)
+}
+
 /// Extracts the number form the string, if there is an error, report it
-fn extract<T>(number_str: Option<&str>, line_number: usize, elm: &str, unit: &str) -> T
+fn extract<T>(number_str: Option<&str>, elm: &str, unit: &str) -> Result<T, ParseError>
     where T: FromStr + Display, <T as FromStr>::Err: Display {
     match number_str {
         Some(a) => match a.parse::<T>() {
-            Ok(n) => n,
+            Ok(n) => Ok(n),
             Err(_) => {
-                panic!("Line {}, {} data incorrectly formatted:'{}' -> '{}'", line_number, unit, elm, a);
+                Err(ParseError::Column(format!("{} data incorrectly formatted:'{}' -> '{}'", unit, elm, a)))
             }
         },
         None => {
-            let msg = format!("Line {}, {} data incorrectly formatted (not found): {}", line_number, unit, elm);
-            panic!(msg);
+            Err(ParseError::Column(format!("{} data incorrectly formatted (not found): {}", unit, elm)))
         }
     }
 }
 
-/// Invarent: line must not be empty
-fn formatter(tx_formatter: Sender<Option<InputRow>>, rx_producer: Receiver<Option<(usize, String)>>, tick: Vec<TickDescription>) {
-    while let Some((line_number, line)) = rx_producer.recv().expect("Unable to receive from channel") {
-        let mut datetime: Option<DateTime<Utc>> = None;
-        let mut ask: Option<f32> = None;
-        let mut bid: Option<f32> = None;
-
-        let cols = line.split(',');
-        if cols.clone().collect::<Vec<_>>().len() != tick.len() {
-            panic!("Invalid line {}: '{}'", line_number, line)
+/// Reads the "20161101 22:30:03.617" datetime layout
+fn fixed_datetime(elm: &str) -> Result<NaiveDateTime, ParseError> {
+    // "20161101 22:30:03.617"
+    //  ____ [0..4] year
+    //      __ [4..6] month
+    //        __[6..8] day
+    //           __ [9..11] hour
+    //              __ [12..14] minute
+    //                 __ [15..17] second
+    //                    ___ [18..] fraction of a second (up to nanoseconds)
+    let year: i32 = extract(elm.get(0..4), elm, "year")?;
+    let month: u32 = extract(elm.get(4..6), elm, "month")?;
+    let day: u32 = extract(elm.get(6..8), elm, "day")?;
+    let hour: u32 = extract(elm.get(9..11), elm, "hour")?;
+    let minute: u32 = extract(elm.get(12..14), elm, "minute")?;
+    let second: u32 = extract(elm.get(15..17), elm, "second")?;
+    let nano: u32 = match elm.get(17..) {
+        Some("") | None => 0,
+        Some(fraction) => match fraction.strip_prefix('.') {
+            Some(digits) if !digits.is_empty() && digits.len() <= 9 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+                extract::<u32>(Some(digits), elm, "fraction")? * 10u32.pow(9 - digits.len() as u32)
+            },
+            _ => return Err(ParseError::Column(format!("fraction data incorrectly formatted:'{}' -> '{}'", elm, fraction)))
         }
-        for (desc, elm) in tick.iter().zip(cols) {
-            match *desc {
-                TickDescription::DateTime => {
-                    // "20161101 22:30:03.617"
-                    //  ____ [0..5] year
-                    //      __ [5..7] month
-                    //        __[7..9] day
-                    //           __ [10..12] hour
-                    //              __ [13..15] minute
-                    //                 __ [16..18] second
-                    //                    __ [19..] millis
-                    let year: i32 = extract(elm.get(0..4), line_number, elm, "year");
-                    let month: u32 = extract(elm.get(4..6), line_number, elm, "month");
-                    let day: u32 = extract(elm.get(6..8), line_number, elm, "day");
-                    let hour: u32 = extract(elm.get(9..11), line_number, elm, "hour");
-                    let minute: u32 = extract(elm.get(12..14), line_number, elm, "minute");
-                    let second: u32 = extract(elm.get(15..17), line_number, elm, "second");
-                    datetime = Some(Utc.ymd(year, month, day).and_hms(hour, minute, second));
-                },
-                TickDescription::Ask => {
-                    ask = Some(elm.parse::<f32>().expect(&format!("Line {}, column {} not a number", line_number, elm)));
-                },
-                TickDescription::Bid => {
-                    bid = Some(elm.parse::<f32>().expect(&format!("Line {}, column {} not a number", line_number, elm)));
-                },
-                TickDescription::Filler => { /* skip */ }
+    };
+    match NaiveDate::from_ymd_opt(year, month, day).and_then(|d| d.and_hms_nano_opt(hour, minute, second, nano)) {
+        Some(datetime) => Ok(datetime),
+        None => Err(ParseError::Column(format!("datetime does not exist: {}", elm)))
+    }
+}
+
+/// Reads the datetime with a strftime style pattern. Patterns with an offset
+/// (%z) are converted to UTC, others are read in the zone. Patterns without a
+/// time are read as midnight.
+fn pattern_datetime(elm: &str, pattern: &str, zone: &Zone) -> Result<DateTime<Utc>, ParseError> {
+    let datetime = if pattern.contains("%z") || pattern.contains("%:z") || pattern.contains("%#z") {
+        DateTime::parse_from_str(elm, pattern).map(|d| d.with_timezone(&Utc)).ok()
+    } else {
+        NaiveDateTime::parse_from_str(elm, pattern)
+            .or_else(|_| NaiveDate::parse_from_str(elm, pattern).map(|d| d.and_hms(0, 0, 0)))
+            .map(|d| zone.instant(d))
+            .ok()
+    };
+    match datetime {
+        Some(datetime) => Ok(datetime),
+        None => Err(ParseError::Column(format!("datetime does not match '{}': {}", pattern, elm)))
+    }
+}
+
+/// Reads an epoch timestamp, the fraction (if any) is a fraction of the unit
+fn epoch_datetime(elm: &str, unit: &EpochUnit) -> Result<DateTime<Utc>, ParseError> {
+    let (integer, fraction) = match elm.find('.') {
+        Some(i) => (&elm[..i], &elm[i + 1..]),
+        None => (elm, "")
+    };
+    let value: i64 = extract(Some(integer), elm, "epoch")?;
+    let unit = match *unit {
+        EpochUnit::Auto => {
+            // the digits of timestamps from 1973 until 5138
+            match integer.trim_start_matches('-').len() {
+                0..=11 => EpochUnit::Seconds,
+                12..=14 => EpochUnit::Milliseconds,
+                15..=17 => EpochUnit::Microseconds,
+                _ => EpochUnit::Nanoseconds
             }
+        },
+        ref unit => unit.clone()
+    };
+    // the number of digits in a nanosecond of the unit
+    let places: usize = match unit {
+        EpochUnit::Seconds => 9,
+        EpochUnit::Milliseconds => 6,
+        EpochUnit::Microseconds => 3,
+        _ => 0
+    };
+    if fraction.len() > places || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ParseError::Column(format!("epoch fraction incorrectly formatted:'{}' -> '{}'", elm, fraction)));
+    }
+    let scale = 10i64.pow((places - fraction.len()) as u32);
+    let fraction: i64 = if fraction.is_empty() { 0 } else { extract::<i64>(Some(fraction), elm, "epoch fraction")? * scale };
+    let fraction = if integer.starts_with('-') { -fraction } else { fraction };
+    match value.checked_mul(10i64.pow(places as u32)).and_then(|nanos| nanos.checked_add(fraction)) {
+        Some(nanos) => Ok(Utc.timestamp_nanos(nanos)),
+        None => Err(ParseError::Column(format!("epoch out of range: {}", elm)))
+    }
+}
+
+/// Reads the datetime column in the format of the input
+fn read_datetime(elm: &str, format: &TickFormat) -> Result<DateTime<Utc>, ParseError> {
+    match format.datetime {
+        DateTimeFormat::Fixed => Ok(format.zone.instant(fixed_datetime(elm)?)),
+        DateTimeFormat::Pattern(ref pattern) => pattern_datetime(elm, pattern, &format.zone),
+        DateTimeFormat::Epoch(ref unit) => epoch_datetime(elm, unit)
+    }
+}
+
+/// Reads a single line of bar data. Sides without all of their open, high,
+/// low and close columns are left out of the bar.
+fn parse_bar(line: &str, columns: &[Column], format: &TickFormat) -> Result<InputRow, ParseError> {
+    let mut datetime: Option<DateTime<Utc>> = None;
+    // the open, high, low and close of the ask, bid and mid
+    let mut prices: [[Option<Decimal>; 4]; 3] = [[None; 4]; 3];
+    let mut total: Option<Decimal> = None;
+    let mut volume = Volume::default();
+    let mut ticks: Option<Decimal> = None;
+
+    let cols = format.delimiter.split(line);
+    if cols.len() != columns.len() {
+        return Err(ParseError::InvalidLine);
+    }
+    for (column, elm) in columns.iter().zip(cols) {
+        let unit = column.name();
+        match *column {
+            Column::DateTime => datetime = Some(read_datetime(elm, format)?),
+            Column::Price(side, aggregate) => {
+                let side = match side {
+                    AskBid::Ask => 0,
+                    AskBid::Bid => 1,
+                    AskBid::Mid => 2
+                };
+                let index = match aggregate {
+                    Aggregate::Open => 0,
+                    Aggregate::High => 1,
+                    Aggregate::Low => 2,
+                    Aggregate::Close => 3,
+                    Aggregate::Statistic(_) => continue
+                };
+                prices[side][index] = Some(extract(Some(elm), elm, &unit)?);
+            },
+            Column::Extra(ExtraColumn::Volume) => total = Some(extract(Some(elm), elm, &unit)?),
+            Column::Extra(ExtraColumn::AskVolume) => volume.ask = extract(Some(elm), elm, &unit)?,
+            Column::Extra(ExtraColumn::BidVolume) => volume.bid = extract(Some(elm), elm, &unit)?,
+            Column::Extra(ExtraColumn::TickCount) => ticks = Some(extract(Some(elm), elm, &unit)?),
+            Column::Extra(_) => { /* skip */ }
+        }
+    }
+
+    volume.total = total.unwrap_or(volume.ask + volume.bid);
+    let ohlc = |side: [Option<Decimal>; 4]| match side {
+        [Some(open), Some(high), Some(low), Some(close)] => Some(Ohlc { open, high, low, close }),
+        _ => None
+    };
+    let bar = Bar { ask: ohlc(prices[0]), bid: ohlc(prices[1]), mid: ohlc(prices[2]), ticks };
+    // a side missing from the bars closes at the price of another side
+    let closes: Vec<Decimal> = [bar.ask, bar.bid, bar.mid].iter().filter_map(|side| side.map(|ohlc| ohlc.close)).collect();
+    match (datetime, closes.first()) {
+        (Some(datetime), Some(&first)) => Ok(InputRow {
+            datetime,
+            ask: bar.ask.map_or(first, |ohlc| ohlc.close),
+            bid: bar.bid.map_or(first, |ohlc| ohlc.close),
+            volume,
+            bar: Some(Box::new(bar))
+        }),
+        _ => Err(ParseError::InvalidLine)
+    }
+}
+
+/// Reads a single line of tick data, or of bar data with --bars
+pub fn parse(line: &str, format: &TickFormat) -> Result<InputRow, ParseError> {
+    if let Some(ref columns) = format.bars {
+        return parse_bar(line, columns, format);
+    }
+    let mut datetime: Option<DateTime<Utc>> = None;
+    let mut ask: Option<Decimal> = None;
+    let mut bid: Option<Decimal> = None;
+    let mut total: Option<Decimal> = None;
+    let mut volume = Volume::default();
+
+    let cols = format.delimiter.split(line);
+    if cols.len() != format.tick.len() {
+        return Err(ParseError::InvalidLine);
+    }
+    for (desc, elm) in format.tick.iter().zip(cols) {
+        match *desc {
+            TickDescription::DateTime => {
+                datetime = Some(read_datetime(elm, format)?);
+            },
+            TickDescription::Ask => {
+                ask = Some(extract(Some(elm), elm, "ask")?);
+            },
+            TickDescription::Bid => {
+                bid = Some(extract(Some(elm), elm, "bid")?);
+            },
+            TickDescription::Volume => {
+                total = Some(extract(Some(elm), elm, "volume")?);
+            },
+            TickDescription::AskVolume => {
+                volume.ask = extract(Some(elm), elm, "ask volume")?;
+            },
+            TickDescription::BidVolume => {
+                volume.bid = extract(Some(elm), elm, "bid volume")?;
+            },
+            TickDescription::Filler => { /* skip */ }
         }
+    }
 
+    // without a total volume column the total is the ask and bid volume
+    volume.total = total.unwrap_or(volume.ask + volume.bid);
+    match (datetime, ask, bid) {
+        (Some(datetime), Some(ask), Some(bid)) => Ok(InputRow::new(datetime, ask, bid, volume)),
+        _ => Err(ParseError::InvalidLine)
+    }
+}
 
-        if datetime == None || ask == None || bid == None {
-            panic!("Invalid line {}: '{}'", line_number, line);
+/// Invarent: line must not be empty
+fn formatter(tx_formatter: Sender<Option<InputRow>>, rx_producer: Receiver<Option<(usize, String)>>, format: TickFormat) {
+    let mut format = format;
+    // the lines at the start of the input that may be headers
+    let mut headers = format.header_lines();
+    while let Some((line_number, line)) = rx_producer.recv().expect("Unable to receive from channel") {
+        if headers > 0 {
+            headers -= 1;
+            if format.is_header(&line) {
+                format = match format.with_header(&line) {
+                    Ok(format) => format,
+                    Err(e) => panic!("Line {}, {}", line_number, e)
+                };
+                continue;
+            }
+            headers = 0;
         }
-        // errors should not occer
-        let datetime: DateTime<Utc> = datetime.unwrap();
-        let ask: f32 = ask.unwrap();
-        let bid: f32 = bid.unwrap();
-        let row = InputRow { datetime: datetime, ask: ask, bid: bid };
+        let row = match parse(&line, &format) {
+            Ok(row) => row,
+            Err(ParseError::InvalidLine) => panic!("Invalid line {}: '{}'", line_number, line),
+            Err(ParseError::Column(e)) => panic!("Line {}, {}", line_number, e)
+        };
         tx_formatter.send(Some(row)).expect("Could not send row data from the producer");
     }
     tx_formatter.send(None).expect("Cannot send None");
@@ -105,15 +470,41 @@ fn formatter(tx_formatter: Sender<Option<InputRow>>, rx_producer: Receiver<Optio
 #[cfg(test)]
 mod test {
     use super::*;
+    use template;
 
     // helper method to generate a filter for the input line data
-    fn gen_td() -> Vec<TickDescription> {
-        vec![
-            TickDescription::Filler,
-            TickDescription::DateTime,
-            TickDescription::Ask,
-            TickDescription::Bid
-        ]
+    fn gen_td() -> TickFormat {
+        TickFormat {
+            tick: vec![
+                TickDescription::Filler,
+                TickDescription::DateTime,
+                TickDescription::Ask,
+                TickDescription::Bid
+            ],
+            datetime: DateTimeFormat::Fixed,
+            zone: Zone::utc(),
+            delimiter: Delimiter::Char(','),
+            header: Header::Auto,
+            columns: None,
+            bars: None
+        }
+    }
+
+    // helper method to generate a format that reads the columns by name
+    fn gen_names() -> TickFormat {
+        TickFormat {
+            columns: Some(ColumnNames {
+                datetime: String::from("Timestamp"),
+                ask: String::from("Ask"),
+                bid: String::from("Bid")
+            }),
+            ..gen_td()
+        }
+    }
+
+    // helper method to generate a format with a datetime pattern
+    fn gen_pattern(pattern: &str) -> TickFormat {
+        TickFormat { datetime: DateTimeFormat::Pattern(String::from(pattern)), ..gen_td() }
     }
 
     #[test]
@@ -123,11 +514,7 @@ mod test {
         tx.send(Some((1, String::from("AUD/USD,20161101 22:30:05.632,0.76551,0.76541")))).expect("Could not send line");
         tx.send(None).expect("Cannot send None");
         formatter(txf, rx, gen_td());
-        assert_eq!(rxf.recv().unwrap(), Some(InputRow{
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632),
-            ask: 0.76551,
-            bid: 0.76541
-        }));
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632), dec!(0.76551), dec!(0.76541), Volume::default())));
         assert_eq!(rxf.recv().unwrap(), None);
     }
 
@@ -140,21 +527,9 @@ mod test {
         tx.send(Some((1, String::from("AUD/USD,20161101 22:30:06.890,0.76549,0.76538")))).expect("Could not send line");
         tx.send(None).expect("Cannot send None");
         formatter(txf, rx, gen_td());
-        assert_eq!(rxf.recv().unwrap(), Some(InputRow{
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632),
-            ask: 0.76551,
-            bid: 0.76541
-        }));
-        assert_eq!(rxf.recv().unwrap(), Some(InputRow{
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 6, 473),
-            ask: 0.76555,
-            bid: 0.76545
-        }));
-        assert_eq!(rxf.recv().unwrap(), Some(InputRow{
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 6, 890),
-            ask: 0.76549,
-            bid: 0.76538
-        }));
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632), dec!(0.76551), dec!(0.76541), Volume::default())));
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 6, 473), dec!(0.76555), dec!(0.76545), Volume::default())));
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 6, 890), dec!(0.76549), dec!(0.76538), Volume::default())));
         assert_eq!(rxf.recv().unwrap(), None);
     }
 
@@ -237,4 +612,205 @@ mod test {
         tx.send(None).expect("Cannot send None");
         formatter(txf, rx, gen_td());
     }
+
+    #[test]
+    #[should_panic(expected = "Line 1, ask data incorrectly formatted:'0.7655x' -> '0.7655x'")]
+    fn faulty_ask() {
+        let (tx, rx) = channel();
+        let (txf, _) = channel();
+        tx.send(Some((1, String::from("AUD/USD,20161101 22:30:05.632,0.7655x,0.76541")))).expect("Could not send line");
+        tx.send(None).expect("Cannot send None");
+        formatter(txf, rx, gen_td());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("AUD/USD,20161101 22:30:05.632,0.76551", &gen_td()), Err(ParseError::InvalidLine));
+        assert_eq!(parse("AUD/USD,20161131 22:30:05.632,0.76551,0.76541", &gen_td()),
+            Err(ParseError::Column(String::from("datetime does not exist: 20161131 22:30:05.632"))));
+        assert_eq!(parse("x,20161101 22:30:03é,0.76551,0.76541", &gen_td()),
+            Err(ParseError::Column(String::from("fraction data incorrectly formatted:'20161101 22:30:03é' -> 'é'"))));
+    }
+
+    #[test]
+    fn datetime_patterns() {
+        let expected = Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617);
+        let row = parse("AUD/USD,2016.11.01 22:30:03.617,0.76551,0.76541", &gen_pattern("%Y.%m.%d %H:%M:%S%.f")).unwrap();
+        assert_eq!(row.datetime, expected);
+        let row = parse("AUD/USD,2016-11-01T22:30:03.617Z,0.76551,0.76541", &gen_pattern("%Y-%m-%dT%H:%M:%S%.fZ")).unwrap();
+        assert_eq!(row.datetime, expected);
+        let row = parse("AUD/USD,2016-11-02T00:30:03.617+02:00,0.76551,0.76541", &gen_pattern("%Y-%m-%dT%H:%M:%S%.f%:z")).unwrap();
+        assert_eq!(row.datetime, expected);
+        let row = parse("AUD/USD,01/11/2016 22:30:03,0.76551,0.76541", &gen_pattern("%d/%m/%Y %H:%M:%S")).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms(22, 30, 3));
+        let row = parse("AUD/USD,01/11/2016,0.76551,0.76541", &gen_pattern("%d/%m/%Y")).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Line 1, datetime does not match '%Y.%m.%d %H:%M:%S%.f': 20161101 22:30:03.617")]
+    fn wrong_pattern() {
+        let (tx, rx) = channel();
+        let (txf, _) = channel();
+        tx.send(Some((1, String::from("AUD/USD,20161101 22:30:03.617,0.76551,0.76541")))).expect("Could not send line");
+        tx.send(None).expect("Cannot send None");
+        formatter(txf, rx, gen_pattern("%Y.%m.%d %H:%M:%S%.f"));
+    }
+
+    #[test]
+    fn sub_second_precision() {
+        let row = parse("AUD/USD,20161101 22:30:03,0.76551,0.76541", &gen_td()).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms(22, 30, 3));
+        let row = parse("AUD/USD,20161101 22:30:03.6,0.76551,0.76541", &gen_td()).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 600));
+        let row = parse("AUD/USD,20161101 22:30:03.617254,0.76551,0.76541", &gen_td()).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms_micro(22, 30, 3, 617254));
+        let row = parse("AUD/USD,20161101 22:30:03.617254093,0.76551,0.76541", &gen_td()).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms_nano(22, 30, 3, 617254093));
+        assert_eq!(parse("AUD/USD,20161101 22:30:03:617,0.76551,0.76541", &gen_td()),
+            Err(ParseError::Column(String::from("fraction data incorrectly formatted:'20161101 22:30:03:617' -> ':617'"))));
+    }
+
+    #[test]
+    fn epoch_timestamps() {
+        let expected = Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617);
+        let epoch = |elm: &str, unit: EpochUnit| {
+            let format = TickFormat { datetime: DateTimeFormat::Epoch(unit), ..gen_td() };
+            parse(&format!("AUD/USD,{},0.76551,0.76541", elm), &format).map(|row| row.datetime)
+        };
+        assert_eq!(epoch("1478039403.617", EpochUnit::Seconds), Ok(expected));
+        assert_eq!(epoch("1478039403617", EpochUnit::Milliseconds), Ok(expected));
+        assert_eq!(epoch("1478039403617000.0", EpochUnit::Microseconds), Ok(expected));
+        assert_eq!(epoch("1478039403.617", EpochUnit::Auto), Ok(expected));
+        assert_eq!(epoch("1478039403617", EpochUnit::Auto), Ok(expected));
+        assert_eq!(epoch("1478039403617000", EpochUnit::Auto), Ok(expected));
+        assert_eq!(epoch("1478039403617000000", EpochUnit::Auto), Ok(expected));
+        assert_eq!(epoch("1478039403", EpochUnit::Auto), Ok(Utc.ymd(2016, 11, 1).and_hms(22, 30, 3)));
+        assert_eq!(epoch("1478039403617.5", EpochUnit::Auto), Ok(Utc.ymd(2016, 11, 1).and_hms_micro(22, 30, 3, 617500)));
+        assert_eq!(epoch("1478039403617.5", EpochUnit::Nanoseconds),
+            Err(ParseError::Column(String::from("epoch fraction incorrectly formatted:'1478039403617.5' -> '5'"))));
+    }
+
+    #[test]
+    fn input_zone() {
+        // midnight in GMT+2 is 22:00 UTC the day before
+        let format = TickFormat { zone: Zone::parse("+02:00").unwrap(), ..gen_td() };
+        let row = parse("AUD/USD,20161102 00:30:03.617,0.76551,0.76541", &format).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617));
+        // datetimes with an offset or from the epoch are not moved
+        let format = TickFormat { datetime: DateTimeFormat::Epoch(EpochUnit::Auto), ..format };
+        let row = parse("AUD/USD,1478039403617,0.76551,0.76541", &format).unwrap();
+        assert_eq!(row.datetime, Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617));
+    }
+
+    #[test]
+    fn delimiters() {
+        let expected = Ok(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632));
+        let format = TickFormat { delimiter: Delimiter::Char('\t'), ..gen_td() };
+        assert_eq!(parse("AUD/USD\t20161101 22:30:05.632\t0.76551\t0.76541", &format).map(|r| r.datetime), expected);
+        let format = TickFormat { delimiter: Delimiter::Char(';'), ..gen_td() };
+        assert_eq!(parse("AUD/USD;20161101 22:30:05.632;0.76551;0.76541", &format).map(|r| r.datetime), expected);
+        let format = TickFormat { delimiter: Delimiter::Whitespace, datetime: DateTimeFormat::Epoch(EpochUnit::Auto), ..gen_td() };
+        assert_eq!(parse("AUD/USD   1478039405632 \t 0.76551  0.76541", &format).map(|r| r.datetime), expected);
+        // the datetime is split in two
+        let format = TickFormat { delimiter: Delimiter::Whitespace, ..gen_td() };
+        assert_eq!(parse("AUD/USD 20161101 22:30:05.632 0.76551 0.76541", &format), Err(ParseError::InvalidLine));
+    }
+
+    #[test]
+    fn header_detection() {
+        let format = gen_td();
+        assert!(format.is_header("Symbol,Timestamp,Ask,Bid"));
+        assert!(!format.is_header("AUD/USD,20161101 22:30:05.632,0.76551,0.76541"));
+        assert!(!format.is_header(",,,"));
+        assert!(!format.is_header("Symbol,Timestamp,Ask"));
+        assert!(!format.is_header("AUD/USD,,0.76551,0.76541"));
+        assert!(gen_names().is_header("AUD/USD,20161101 22:30:05.632,0.76551,0.76541"));
+    }
+
+    #[test]
+    fn skip_header() {
+        let (tx, rx) = channel();
+        let (txf, rxf) = channel();
+        tx.send(Some((1, String::from("Symbol,Timestamp,Ask,Bid")))).expect("Could not send line");
+        tx.send(Some((2, String::from("AUD/USD,20161101 22:30:05.632,0.76551,0.76541")))).expect("Could not send line");
+        tx.send(None).expect("Cannot send None");
+        formatter(txf, rx, gen_td());
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632), dec!(0.76551), dec!(0.76541), Volume::default())));
+        assert_eq!(rxf.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn columns_by_name() {
+        let (tx, rx) = channel();
+        let (txf, rxf) = channel();
+        tx.send(Some((1, String::from("Bid,\"ask\",Volume,TIMESTAMP")))).expect("Could not send line");
+        tx.send(Some((2, String::from("0.76541,0.76551,1.5,20161101 22:30:05.632")))).expect("Could not send line");
+        tx.send(None).expect("Cannot send None");
+        formatter(txf, rx, gen_names());
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 632), dec!(0.76551), dec!(0.76541), Volume::default())));
+        assert_eq!(rxf.recv().unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Line 1, column 'Bid' not found in header 'Timestamp,Ask'")]
+    fn missing_named_column() {
+        let (tx, rx) = channel();
+        let (txf, _) = channel();
+        tx.send(Some((1, String::from("Timestamp,Ask")))).expect("Could not send line");
+        tx.send(None).expect("Cannot send None");
+        formatter(txf, rx, gen_names());
+    }
+
+    #[test]
+    fn prices_keep_digits() {
+        let row = parse("USD/JPY,20161101 22:30:03.617,104.830,104.8270", &gen_td()).unwrap();
+        assert_eq!(row.ask.to_string(), "104.830");
+        assert_eq!(row.bid.to_string(), "104.8270");
+        let row = parse("US500,20161101 22:30:03.617,12345.67,1.23456789012", &gen_td()).unwrap();
+        assert_eq!(row.ask.to_string(), "12345.67");
+        assert_eq!(row.bid.to_string(), "1.23456789012");
+    }
+
+    #[test]
+    fn volumes() {
+        let mut format = gen_td();
+        format.tick.push(TickDescription::AskVolume);
+        format.tick.push(TickDescription::BidVolume);
+        let row = parse("AUD/USD,20161101 22:30:03.617,0.76551,0.76541,1.5,2.25", &format).unwrap();
+        assert_eq!(row.volume, Volume { total: dec!(3.75), ask: dec!(1.5), bid: dec!(2.25) });
+        format.tick.push(TickDescription::Volume);
+        let row = parse("AUD/USD,20161101 22:30:03.617,0.76551,0.76541,1.5,2.25,4", &format).unwrap();
+        assert_eq!(row.volume.total, dec!(4));
+        assert_eq!(parse("AUD/USD,20161101 22:30:03.617,0.76551,0.76541,1.5,many,4", &format),
+            Err(ParseError::Column(String::from("bid volume data incorrectly formatted:'many' -> 'many'"))));
+    }
+
+    #[test]
+    fn bars() {
+        let format = TickFormat {
+            datetime: DateTimeFormat::Pattern(String::from("%Y-%m-%d %H:%M:%S UTC")),
+            bars: Some(template::parse("time,bid.open,bid.high,bid.low,bid.close,volume,ticks").unwrap()),
+            ..gen_td()
+        };
+        let (tx, rx) = channel();
+        let (txf, rxf) = channel();
+        tx.send(Some((1, String::from(",bid,bid,bid,bid,,")))).expect("Could not send line");
+        tx.send(Some((2, String::from("datetime,open,high,low,close,volume,ticks")))).expect("Could not send line");
+        tx.send(Some((3, String::from("2016-11-01 22:30:00 UTC,0.76551,0.76572,0.76541,0.76566,5.5,4")))).expect("Could not send line");
+        tx.send(None).expect("Cannot send None");
+        formatter(txf, rx, format.clone());
+        let bid = Ohlc { open: dec!(0.76551), high: dec!(0.76572), low: dec!(0.76541), close: dec!(0.76566) };
+        assert_eq!(rxf.recv().unwrap(), Some(InputRow {
+            datetime: Utc.ymd(2016, 11, 1).and_hms(22, 30, 0),
+            ask: dec!(0.76566),
+            bid: dec!(0.76566),
+            volume: Volume { total: dec!(5.5), ask: dec!(0), bid: dec!(0) },
+            bar: Some(Box::new(Bar { ask: None, bid: Some(bid), mid: None, ticks: Some(dec!(4)) }))
+        }));
+        assert_eq!(rxf.recv().unwrap(), None);
+        assert_eq!(parse("2016-11-01 22:30:00 UTC,0.76551,0.76572,0.76541,0.76566,5.5", &format), Err(ParseError::InvalidLine));
+        assert_eq!(parse("2016-11-01 22:30:00 UTC,0.76551,NaN,0.76541,0.76566,5.5,4", &format),
+            Err(ParseError::Column(String::from("bid.high data incorrectly formatted:'NaN' -> 'NaN'"))));
+    }
 }
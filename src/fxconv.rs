@@ -6,8 +6,114 @@ pub enum AskBidOption {
     BidFirst
 }
 
+/// The prices of a group of OHLC columns
 #[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
 pub enum AskBid {
     Ask,
-    Bid
+    Bid,
+    /// The mean of the ask and bid of each tick
+    Mid
+}
+
+/// An aggregate of the prices of a bar written after its OHLC columns
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum Statistic {
+    /// Each price weighted by the time until the next tick (or the bar close)
+    Twap,
+    /// Each price weighted by the volume of its tick
+    Vwap,
+    Mean,
+    Median,
+    /// The population standard deviation
+    StdDev
+}
+
+/// The columns written after the prices of a bar
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum ExtraColumn {
+    Volume,
+    AskVolume,
+    BidVolume,
+    SpreadMin,
+    SpreadMax,
+    SpreadMean,
+    SpreadClose,
+    TickCount
+}
+
+/// When a bar closes
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum BarType {
+    /// At the end of the timeframe
+    Time,
+    /// After the number of ticks, or at the end of the timeframe
+    Ticks(usize)
+}
+
+/// How timeframes without ticks are written
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum Fill {
+    /// Skipped
+    None,
+    /// A flat bar at the close of the previous bar
+    Forward,
+    /// A row without prices
+    NaN
+}
+
+/// What is done with ticks outside of the trading session
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum OutOfSession {
+    /// The ticks are dropped
+    Exclude,
+    /// The ticks are kept and reported
+    Warn
+}
+
+/// Which datetime labels an output bar
+#[derive(Clone)]
+pub enum Label {
+    Open,
+    Close,
+    FirstTick,
+    LastTick
+}
+
+/// Separates the columns of the input and output data
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone)]
+pub enum Delimiter {
+    Char(char),
+    /// Any run of spaces and tabs
+    Whitespace
+}
+
+impl Delimiter {
+    pub fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match *self {
+            Delimiter::Char(c) => line.split(c).collect(),
+            Delimiter::Whitespace => line.split_whitespace().collect()
+        }
+    }
+
+    /// The text written between output columns
+    pub fn separator(&self) -> String {
+        match *self {
+            Delimiter::Char(c) => c.to_string(),
+            Delimiter::Whitespace => String::from(" ")
+        }
+    }
 }
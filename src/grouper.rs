@@ -1,100 +1,114 @@
 use chrono::prelude::*;
+use rust_decimal::Decimal;
 use market::timeframe::TimeFrame;
-use market::timeframe::TimeUnit;
 use std::thread;
 use std::sync::mpsc::channel;
-use time::Duration;
+use formatter::Bar;
 use formatter::InputRow;
+use formatter::Volume;
+use fxconv::BarType;
+use fxconv::Fill;
+use market::session::Session;
 use std::sync::mpsc::{Sender, Receiver};
 
 #[derive(Debug)]
 #[derive(PartialEq)]
 pub struct TickGroup {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
     pub datetimes: Vec<DateTime<Utc>>,
-    pub asks: Vec<f32>,
-    pub bids: Vec<f32>
+    pub asks: Vec<Decimal>,
+    pub bids: Vec<Decimal>,
+    /// The total volume of each tick
+    pub volumes: Vec<Decimal>,
+    /// The volume of all the ticks in the group
+    pub volume: Volume,
+    /// The input bars of the group when resampling bars, each with a datetime,
+    /// ask and bid above
+    pub bars: Vec<Bar>
 }
 
 impl TickGroup {
-    fn new() -> TickGroup {
+    /// An empty group for the timeframe from start (inclusive) to end (exclusive)
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> TickGroup {
         TickGroup {
+            start,
+            end,
             datetimes: Vec::new(),
             asks: Vec::new(),
-            bids: Vec::new()
+            bids: Vec::new(),
+            volumes: Vec::new(),
+            volume: Volume::default(),
+            bars: Vec::new()
         }
     }
 
-    /// Clears the current contents and returns that contents in a new group
-    fn dump(&mut self) -> TickGroup {
-        let group = TickGroup {
-            datetimes: self.datetimes.clone(),
-            asks: self.asks.clone(),
-            bids: self.bids.clone()
-        };
-        self.datetimes.clear();
-        self.asks.clear();
-        self.bids.clear();
-        group
-    }
-
-    fn push(&mut self, datetime: DateTime<Utc>, ask: f32, bid: f32) {
-        self.datetimes.push(datetime);
-        self.asks.push(ask);
-        self.bids.push(bid);
-    }
-
-    fn len(&self) -> usize {
-        self.datetimes.len()
-    }
-
-    fn is_empty(&self) -> bool {
-        self.len() == 0
+    fn push(&mut self, row: InputRow) {
+        self.datetimes.push(row.datetime);
+        self.asks.push(row.ask);
+        self.bids.push(row.bid);
+        self.volumes.push(row.volume.total);
+        self.volume.total += row.volume.total;
+        self.volume.ask += row.volume.ask;
+        self.volume.bid += row.volume.bid;
+        if let Some(bar) = row.bar {
+            self.bars.push(*bar);
+        }
     }
 }
 
-pub fn create(rx_formatter: Receiver<Option<InputRow>>, time_frame: TimeFrame)  -> (thread::JoinHandle<()>, Receiver<Option<TickGroup>>) {
+pub fn create(rx_formatter: Receiver<Option<InputRow>>, time_frame: TimeFrame, bar_type: BarType, fill: Fill, session: Option<Session>)  -> (thread::JoinHandle<()>, Receiver<Option<TickGroup>>) {
     let (tx_grouper, rx_grouper) = channel();
     let grouper_thread = thread::Builder::new().name("grouper".to_string()).spawn(move || {
-        grouper(tx_grouper, rx_formatter, time_frame);
+        grouper(tx_grouper, rx_formatter, time_frame, bar_type, fill, session);
     });
     (grouper_thread.expect("Thread did not spawn correctly"), rx_grouper)
 }
 
-fn grouper(tx_grouper: Sender<Option<TickGroup>>, rx_formatter: Receiver<Option<InputRow>>, time_frame: TimeFrame) {
-    // Select unit measurment
-    let duration_func = match  *time_frame.unit() {
-        TimeUnit::Second => Duration::seconds,
-        TimeUnit::Minute => Duration::minutes,
-        TimeUnit::Hour => Duration::hours,
-        TimeUnit::Day => Duration::days,
-        TimeUnit::Week => Duration::weeks
-    };
-
-    let over_timeframe = |a: DateTime<Utc>,b: DateTime<Utc>| {
-        a.signed_duration_since(b) >= duration_func(time_frame.len() as i64)
-    };
-
+fn grouper(tx_grouper: Sender<Option<TickGroup>>, rx_formatter: Receiver<Option<InputRow>>, time_frame: TimeFrame, bar_type: BarType, fill: Fill, session: Option<Session>) {
     // to store the data in the frame
-    let mut group = TickGroup::new();
-    let mut first: Option<DateTime<Utc>> = None; // first datetime in timeframe
+    let mut group: Option<TickGroup> = None;
 
     while let Some(row) = rx_formatter.recv().expect("Unable to receive from channel") {
-        // if not initialized, then init
-        if first == None {
-            first = Some(row.datetime);
-        }
-
-        if over_timeframe(row.datetime, first.unwrap()) {
-            while over_timeframe(row.datetime, first.unwrap()) {
-                first = Some(first.unwrap() + duration_func(time_frame.len() as i64));
+        let over_timeframe = match group {
+            Some(ref group) => row.datetime >= group.end,
+            None => true
+        };
+        let full = match (bar_type, &group) {
+            (BarType::Ticks(count), Some(group)) => group.datetimes.len() == count,
+            _ => false
+        };
+        if over_timeframe || full {
+            let start = time_frame.open_of(row.datetime);
+            if let Some(mut group) = group.take() {
+                // full tick bars last until the next tick, others until the end
+                // of their timeframe
+                if full && !over_timeframe {
+                    group.end = row.datetime;
+                }
+                // timeframes without ticks are skipped unless they are filled
+                let mut end = group.end;
+                tx_grouper.send(Some(group)).unwrap();
+                while bar_type == BarType::Time && fill != Fill::None && end < start {
+                    let empty = TickGroup::new(time_frame.open_of(end), time_frame.close_of(end));
+                    end = empty.end;
+                    // nor are those that start while the market is closed
+                    if let Some(ref session) = session {
+                        if !session.is_open(empty.start) {
+                            continue;
+                        }
+                    }
+                    tx_grouper.send(Some(empty)).unwrap();
+                }
             }
-            tx_grouper.send(Some(group.dump())).unwrap();
-            // reset the group
-            group = TickGroup::new();
+            group = Some(match bar_type {
+                BarType::Time => TickGroup::new(start, time_frame.close_of(row.datetime)),
+                BarType::Ticks(_) => TickGroup::new(row.datetime, time_frame.close_of(row.datetime))
+            });
         }
-        group.push(row.datetime, row.ask, row.bid);
+        group.as_mut().unwrap().push(row);
     }
-    if ! group.is_empty() {
+    if let Some(group) = group {
         tx_grouper.send(Some(group)).unwrap();
     }
     tx_grouper.send(None).unwrap();
@@ -103,13 +117,15 @@ fn grouper(tx_grouper: Sender<Option<TickGroup>>, rx_formatter: Receiver<Option<
 #[cfg(test)]
 mod tests {
     use super::*;
+    use market::timeframe::TimeUnit;
+    use market::timezone::Zone;
 
     #[test]
     fn no_data() {
         let (txf, rxf) = channel();
         let (txg, rxg) = channel();
         txf.send(None).expect("Could not send None");
-        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day));
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day), BarType::Time, Fill::None, None);
         assert_eq!(rxg.recv().expect("Failed to recieve"), None);
     }
 
@@ -117,17 +133,16 @@ mod tests {
     fn one() {
         let (txf, rxf) = channel();
         let (txg, rxg) = channel();
-        txf.send(Some(InputRow {
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613),
-            ask: 1.1234,
-            bid: 1.1222
-        })).expect("Could not send None");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613), dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send None");
         txf.send(None).expect("Could not send None");
-        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day));
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day), BarType::Time, Fill::None, None);
         assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
             datetimes: vec![Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613)],
-            asks: vec![1.1234],
-            bids: vec![1.1222]
+            asks: vec![dec!(1.1234)],
+            bids: vec![dec!(1.1222)],
+            volumes: vec![dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(0, 0, 0), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0))
         }));
         assert_eq!(rxg.recv().expect("Failed to recieve"), None);
     }
@@ -136,22 +151,17 @@ mod tests {
     fn two_same_time_frames() {
         let (txf, rxf) = channel();
         let (txg, rxg) = channel();
-        txf.send(Some(InputRow {
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613),
-            ask: 1.1234,
-            bid: 1.1222
-        })).expect("Could not send None");
-        txf.send(Some(InputRow {
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(23, 25, 36, 923),
-            ask: 1.1204,
-            bid: 1.1195
-        })).expect("Could not send None");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613), dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send None");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(23, 25, 36, 923), dec!(1.1204), dec!(1.1195), Volume::default()))).expect("Could not send None");
         txf.send(None).expect("Could not send None");
-        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day));
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day), BarType::Time, Fill::None, None);
         assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
             datetimes: vec![Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613), Utc.ymd(2016, 11, 1).and_hms_milli(23, 25, 36, 923)],
-            asks: vec![1.1234, 1.1204],
-            bids: vec![1.1222, 1.1195]
+            asks: vec![dec!(1.1234), dec!(1.1204)],
+            bids: vec![dec!(1.1222), dec!(1.1195)],
+            volumes: vec![dec!(0), dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(0, 0, 0), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0))
         }));
         assert_eq!(rxg.recv().expect("Failed to recieve"), None);
     }
@@ -160,28 +170,187 @@ mod tests {
     fn two_different_time_frames() {
         let (txf, rxf) = channel();
         let (txg, rxg) = channel();
-        txf.send(Some(InputRow {
-            datetime: Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613),
-            ask: 1.1234,
-            bid: 1.1222
-        })).expect("Could not send None");
-        txf.send(Some(InputRow {
-            datetime: Utc.ymd(2016, 11, 2).and_hms_milli(23, 25, 36, 923),
-            ask: 1.1204,
-            bid: 1.1195
-        })).expect("Could not send None");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613), dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send None");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 2).and_hms_milli(23, 25, 36, 923), dec!(1.1204), dec!(1.1195), Volume::default()))).expect("Could not send None");
         txf.send(None).expect("Could not send None");
-        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day));
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day), BarType::Time, Fill::None, None);
         assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
             datetimes: vec![Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613)],
-            asks: vec![1.1234],
-            bids: vec![1.1222]
+            asks: vec![dec!(1.1234)],
+            bids: vec![dec!(1.1222)],
+            volumes: vec![dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(0, 0, 0), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0))
         }));
         assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
             datetimes: vec![Utc.ymd(2016, 11, 2).and_hms_milli(23, 25, 36, 923)],
-            asks: vec![1.1204],
-            bids: vec![1.1195]
+            asks: vec![dec!(1.1204)],
+            bids: vec![dec!(1.1195)],
+            volumes: vec![dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 11, 2).and_hms(0, 0, 0), Utc.ymd(2016, 11, 3).and_hms(0, 0, 0))
+        }));
+        assert_eq!(rxg.recv().expect("Failed to recieve"), None);
+    }
+
+    #[test]
+    fn calendar_months() {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 1, 31).and_hms(10, 0, 0), dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send row");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 2, 1).and_hms(0, 0, 0), dec!(1.1204), dec!(1.1195), Volume::default()))).expect("Could not send row");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 2, 29).and_hms(23, 59, 59), dec!(1.1301), dec!(1.1290), Volume::default()))).expect("Could not send row");
+        txf.send(None).expect("Could not send None");
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Month), BarType::Time, Fill::None, None);
+        assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
+            datetimes: vec![Utc.ymd(2016, 1, 31).and_hms(10, 0, 0)],
+            asks: vec![dec!(1.1234)],
+            bids: vec![dec!(1.1222)],
+            volumes: vec![dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 1, 1).and_hms(0, 0, 0), Utc.ymd(2016, 2, 1).and_hms(0, 0, 0))
+        }));
+        assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
+            datetimes: vec![Utc.ymd(2016, 2, 1).and_hms(0, 0, 0), Utc.ymd(2016, 2, 29).and_hms(23, 59, 59)],
+            asks: vec![dec!(1.1204), dec!(1.1301)],
+            bids: vec![dec!(1.1195), dec!(1.1290)],
+            volumes: vec![dec!(0), dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 2, 1).and_hms(0, 0, 0), Utc.ymd(2016, 3, 1).and_hms(0, 0, 0))
         }));
         assert_eq!(rxg.recv().expect("Failed to recieve"), None);
     }
+
+    #[test]
+    fn aligned_to_clock() {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        // less than an hour apart but either side of 23:00
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617), dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send row");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(23, 0, 1, 102), dec!(1.1204), dec!(1.1195), Volume::default()))).expect("Could not send row");
+        txf.send(None).expect("Could not send None");
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Hour), BarType::Time, Fill::None, None);
+        assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
+            datetimes: vec![Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617)],
+            asks: vec![dec!(1.1234)],
+            bids: vec![dec!(1.1222)],
+            volumes: vec![dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(22, 0, 0), Utc.ymd(2016, 11, 1).and_hms(23, 0, 0))
+        }));
+        assert_eq!(rxg.recv().expect("Failed to recieve"), Some(TickGroup {
+            datetimes: vec![Utc.ymd(2016, 11, 1).and_hms_milli(23, 0, 1, 102)],
+            asks: vec![dec!(1.1204)],
+            bids: vec![dec!(1.1195)],
+            volumes: vec![dec!(0)],
+            volume: Volume::default(),
+            ..TickGroup::new(Utc.ymd(2016, 11, 1).and_hms(23, 0, 0), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0))
+        }));
+        assert_eq!(rxg.recv().expect("Failed to recieve"), None);
+    }
+
+    #[test]
+    fn volume_sums() {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 5, 613), dec!(1.1234), dec!(1.1222), Volume { total: dec!(3), ask: dec!(1), bid: dec!(2) }))).expect("Could not send row");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 36, 923), dec!(1.1204), dec!(1.1195), Volume { total: dec!(1.75), ask: dec!(0.5), bid: dec!(1.25) }))).expect("Could not send row");
+        txf.send(None).expect("Could not send None");
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Minute), BarType::Time, Fill::None, None);
+        let group = rxg.recv().expect("Failed to recieve").unwrap();
+        assert_eq!(group.volumes, vec![dec!(3), dec!(1.75)]);
+        assert_eq!(group.volume, Volume { total: dec!(4.75), ask: dec!(1.5), bid: dec!(3.25) });
+        assert_eq!(rxg.recv().expect("Failed to recieve"), None);
+    }
+
+    #[test]
+    fn filled_gaps() {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617), dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send row");
+        txf.send(Some(InputRow::new(Utc.ymd(2016, 11, 1).and_hms_milli(22, 33, 1, 102), dec!(1.1204), dec!(1.1195), Volume::default()))).expect("Could not send row");
+        txf.send(None).expect("Could not send None");
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Minute), BarType::Time, Fill::Forward, None);
+        let starts: Vec<(DateTime<Utc>, usize)> = rxg.iter().flatten().map(|group| (group.start, group.datetimes.len())).collect();
+        assert_eq!(starts, vec![
+            (Utc.ymd(2016, 11, 1).and_hms(22, 30, 0), 1),
+            (Utc.ymd(2016, 11, 1).and_hms(22, 31, 0), 0),
+            (Utc.ymd(2016, 11, 1).and_hms(22, 32, 0), 0),
+            (Utc.ymd(2016, 11, 1).and_hms(22, 33, 0), 1)
+        ]);
+    }
+
+    #[test]
+    fn trading_session() {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        // Friday 16:59 in New York, then Sunday 17:00
+        for datetime in &[Utc.ymd(2016, 11, 4).and_hms(20, 59, 0), Utc.ymd(2016, 11, 6).and_hms(22, 0, 0)] {
+            txf.send(Some(InputRow::new(*datetime, dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send row");
+        }
+        txf.send(None).expect("Could not send None");
+        let session = Session::fx();
+        let time_frame = TimeFrame::new(1, TimeUnit::Day).with_zone(session.alignment_zone());
+        grouper(txg, rxf, time_frame, BarType::Time, Fill::Forward, Some(session));
+        // days start at 17:00 in New York and the weekend is not filled
+        let groups: Vec<(DateTime<Utc>, usize)> = rxg.iter().flatten().map(|group| (group.start, group.datetimes.len())).collect();
+        assert_eq!(groups, vec![
+            (Utc.ymd(2016, 11, 3).and_hms(21, 0, 0), 1),
+            (Utc.ymd(2016, 11, 6).and_hms(22, 0, 0), 1)
+        ]);
+    }
+
+    // helper method to group ticks at the datetimes into hourly bars in New York
+    fn new_york_hours(datetimes: &[DateTime<Utc>]) -> Vec<(DateTime<Utc>, DateTime<Utc>, usize)> {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        for datetime in datetimes {
+            txf.send(Some(InputRow::new(*datetime, dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send row");
+        }
+        txf.send(None).expect("Could not send None");
+        let time_frame = TimeFrame::new(1, TimeUnit::Hour).with_zone(Zone::parse("America/New_York").unwrap());
+        grouper(txg, rxf, time_frame, BarType::Time, Fill::None, None);
+        rxg.iter().flatten().map(|group| (group.start, group.end, group.datetimes.len())).collect()
+    }
+
+    #[test]
+    fn daylight_saving_ends() {
+        // 01:30 EDT, 01:30 EST and 02:30 EST, the repeated hour is a bar of its own
+        let groups = new_york_hours(&[Utc.ymd(2016, 11, 6).and_hms(5, 30, 0), Utc.ymd(2016, 11, 6).and_hms(6, 30, 0), Utc.ymd(2016, 11, 6).and_hms(7, 30, 0)]);
+        assert_eq!(groups, vec![
+            (Utc.ymd(2016, 11, 6).and_hms(5, 0, 0), Utc.ymd(2016, 11, 6).and_hms(6, 0, 0), 1),
+            (Utc.ymd(2016, 11, 6).and_hms(6, 0, 0), Utc.ymd(2016, 11, 6).and_hms(7, 0, 0), 1),
+            (Utc.ymd(2016, 11, 6).and_hms(7, 0, 0), Utc.ymd(2016, 11, 6).and_hms(8, 0, 0), 1)
+        ]);
+    }
+
+    #[test]
+    fn daylight_saving_starts() {
+        // 01:30 EST and 03:30 EDT, the clock skips from 02:00 to 03:00
+        let groups = new_york_hours(&[Utc.ymd(2016, 3, 13).and_hms(6, 30, 0), Utc.ymd(2016, 3, 13).and_hms(7, 30, 0)]);
+        assert_eq!(groups, vec![
+            (Utc.ymd(2016, 3, 13).and_hms(6, 0, 0), Utc.ymd(2016, 3, 13).and_hms(7, 0, 0), 1),
+            (Utc.ymd(2016, 3, 13).and_hms(7, 0, 0), Utc.ymd(2016, 3, 13).and_hms(8, 0, 0), 1)
+        ]);
+    }
+
+    #[test]
+    fn tick_bars() {
+        let (txf, rxf) = channel();
+        let (txg, rxg) = channel();
+        // three ticks on the 1st and two on the 2nd
+        for datetime in &[Utc.ymd(2016, 11, 1).and_hms(1, 0, 0), Utc.ymd(2016, 11, 1).and_hms(2, 0, 0), Utc.ymd(2016, 11, 1).and_hms(3, 0, 0),
+            Utc.ymd(2016, 11, 2).and_hms(1, 0, 0), Utc.ymd(2016, 11, 2).and_hms(2, 0, 0)] {
+            txf.send(Some(InputRow::new(*datetime, dec!(1.1234), dec!(1.1222), Volume::default()))).expect("Could not send row");
+        }
+        txf.send(None).expect("Could not send None");
+        grouper(txg, rxf, TimeFrame::new(1, TimeUnit::Day), BarType::Ticks(2), Fill::Forward, None);
+        // bars close after two ticks, lasting until the next tick, or at the end of the day
+        let groups: Vec<(DateTime<Utc>, DateTime<Utc>, usize)> = rxg.iter().flatten().map(|group| (group.start, group.end, group.datetimes.len())).collect();
+        assert_eq!(groups, vec![
+            (Utc.ymd(2016, 11, 1).and_hms(1, 0, 0), Utc.ymd(2016, 11, 1).and_hms(3, 0, 0), 2),
+            (Utc.ymd(2016, 11, 1).and_hms(3, 0, 0), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0), 1),
+            (Utc.ymd(2016, 11, 2).and_hms(1, 0, 0), Utc.ymd(2016, 11, 3).and_hms(0, 0, 0), 2)
+        ]);
+    }
 }
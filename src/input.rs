@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+use bzip2::read::MultiBzDecoder;
+use zip::ZipArchive;
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Bzip2,
+    Zip
+}
+
+/// A single stream of input data. Compressed files are decoded on the fly and
+/// each file inside a zip archive is an input of its own.
+pub struct Input {
+    pub name: String,
+    path: String,
+    compression: Compression,
+    member: Option<usize>
+}
+
+impl Input {
+    /// Uncompressed inputs are files that can be read from any position
+    pub fn is_plain(&self) -> bool {
+        self.compression == Compression::None
+    }
+
+    /// Reads the input with the decoded data
+    pub fn read<F, T>(&self, f: F) -> T where F: FnOnce(&mut dyn Read) -> T {
+        let mut file = File::open(&self.path).unwrap_or_else(|_| panic!("Could not open input file '{}'", self.path));
+        match self.compression {
+            Compression::None => f(&mut file),
+            Compression::Gzip => f(&mut MultiGzDecoder::new(file)),
+            Compression::Xz => f(&mut XzDecoder::new_multi_decoder(file)),
+            Compression::Bzip2 => f(&mut MultiBzDecoder::new(file)),
+            Compression::Zip => {
+                let mut archive = ZipArchive::new(file).unwrap_or_else(|_| panic!("Could not read zip archive '{}'", self.path));
+                let mut member = archive.by_index(self.member.unwrap()).unwrap_or_else(|_| panic!("Could not read '{}'", self.name));
+                f(&mut member)
+            }
+        }
+    }
+
+    /// Opens the uncompressed input file
+    pub fn file(&self) -> File {
+        File::open(&self.path).unwrap_or_else(|_| panic!("Could not open input file '{}'", self.path))
+    }
+}
+
+/// Opens the input file, a zip archive gives an input for each file it holds
+pub fn open(path: &str) -> Vec<Input> {
+    let mut file = File::open(path).unwrap_or_else(|_| panic!("Could not open input file '{}'", path));
+    let mut magic: Vec<u8> = Vec::new();
+    (&mut file).take(6).read_to_end(&mut magic).unwrap_or_else(|_| panic!("Could not read input file '{}'", path));
+    let compression = match detect(&magic) {
+        Some(compression) => compression,
+        None => from_extension(path)
+    };
+
+    if compression != Compression::Zip {
+        return vec![Input { name: String::from(path), path: String::from(path), compression, member: None }];
+    }
+    let mut archive = ZipArchive::new(file).unwrap_or_else(|_| panic!("Could not read zip archive '{}'", path));
+    let mut inputs: Vec<Input> = Vec::new();
+    for index in 0..archive.len() {
+        let member = archive.by_index(index).unwrap_or_else(|_| panic!("Could not read zip archive '{}'", path));
+        if member.is_dir() {
+            continue;
+        }
+        inputs.push(Input {
+            name: format!("{}:{}", path, member.name()),
+            path: String::from(path),
+            compression,
+            member: Some(index)
+        });
+    }
+    inputs
+}
+
+/// Detects the compression from the magic bytes at the start of the file
+fn detect(magic: &[u8]) -> Option<Compression> {
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        Some(Compression::Gzip)
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some(Compression::Xz)
+    } else if magic.starts_with(b"BZh") {
+        Some(Compression::Bzip2)
+    } else if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+        Some(Compression::Zip)
+    } else if magic.len() < 6 {
+        // too short to tell
+        None
+    } else {
+        Some(Compression::None)
+    }
+}
+
+/// Guesses the compression from the file extension
+fn from_extension(path: &str) -> Compression {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("gz") => Compression::Gzip,
+        Some("xz") => Compression::Xz,
+        Some("bz2") => Compression::Bzip2,
+        Some("zip") => Compression::Zip,
+        _ => Compression::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_bytes() {
+        assert_eq!(detect(&[0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00]), Some(Compression::Gzip));
+        assert_eq!(detect(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]), Some(Compression::Xz));
+        assert_eq!(detect(b"BZh91A"), Some(Compression::Bzip2));
+        assert_eq!(detect(b"PK\x03\x04\x14\x00"), Some(Compression::Zip));
+        assert_eq!(detect(b"AUD/USD,20161101"), Some(Compression::None));
+        assert_eq!(detect(b"a,1"), None);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(from_extension("ticks.csv.gz"), Compression::Gzip);
+        assert_eq!(from_extension("ticks.csv.xz"), Compression::Xz);
+        assert_eq!(from_extension("ticks.csv.bz2"), Compression::Bzip2);
+        assert_eq!(from_extension("ticks.zip"), Compression::Zip);
+        assert_eq!(from_extension("ticks.csv"), Compression::None);
+    }
+}
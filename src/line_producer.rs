@@ -1,31 +1,70 @@
-use std::fs::File;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
 use std::io::prelude::*;
+use std::io::BufReader;
 use std::thread;
+use std::str;
+use input::Input;
 
+/// The number of lines that may wait in the channel for the formatter. The
+/// producer blocks when the channel is full so that memory use stays bounded
+/// no matter how large the input file is.
+const LINE_BUFFER: usize = 4096;
 
 /// From the input files, generates lines from file
-pub fn create(input_file: File) -> (thread::JoinHandle<()>, Receiver<Option<(usize, String)>>) {
-    let (tx_rows, rx_rows) = channel();
+pub fn create(input: Input) -> (thread::JoinHandle<()>, Receiver<Option<(usize, String)>>) {
+    let (tx_rows, rx_rows) = sync_channel(LINE_BUFFER);
     let t = thread::Builder::new().name("producer".to_string()).spawn(move || {
-        line_producer(input_file, tx_rows);
+        input.read(|reader| line_producer(reader, tx_rows));
     });
     (t.expect("Thread did not spawn correctly"), rx_rows)
 }
 
-fn line_producer(mut file: File, tx_rows: Sender<Option<(usize, String)>>) {
-    let size = file.metadata().expect("Cannot find file metadata").len() as usize;
-    let mut text: Vec<u8> = vec![0; size];
-    file.read_exact(&mut text).expect(&format!("Could not read input file"));
-    let contents = String::from_utf8(text).expect("Could not convert bytes to string");
-
-    for (line_number, line) in contents.split('\n').enumerate() {
+/// Streams the input line by line, only one line is held in memory at a time
+fn line_producer<R: Read>(input: R, tx_rows: SyncSender<Option<(usize, String)>>) {
+    let mut reader = BufReader::new(input);
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut line_number = 0;
+    loop {
+        bytes.clear();
+        let read = reader.read_until(b'\n', &mut bytes).expect("Could not read input file");
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        let line = match str::from_utf8(&bytes) {
+            Ok(line) => line,
+            Err(_) => panic!("Line {}, could not convert bytes to string", line_number)
+        };
         let line = line.trim();
-            // skip empty line
-            if line.len() == 0 {
-                continue;
-            }
-        tx_rows.send(Some((line_number + 1, String::from(line)))).expect("Could not send row data from the producer");
+        // skip empty line
+        if line.is_empty() {
+            continue;
+        }
+        tx_rows.send(Some((line_number, String::from(line)))).expect("Could not send row data from the producer");
     }
     tx_rows.send(None).expect("Could not send None from the producer");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbered_lines() {
+        let (tx, rx) = sync_channel(LINE_BUFFER);
+        let input = "a,1\r\n\nb,2\n  \nc,3";
+        line_producer(input.as_bytes(), tx);
+        assert_eq!(rx.recv().unwrap(), Some((1, String::from("a,1"))));
+        assert_eq!(rx.recv().unwrap(), Some((3, String::from("b,2"))));
+        assert_eq!(rx.recv().unwrap(), Some((5, String::from("c,3"))));
+        assert_eq!(rx.recv().unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Line 2, could not convert bytes to string")]
+    fn invalid_utf8() {
+        let (tx, _rx) = sync_channel(LINE_BUFFER);
+        let input: &[u8] = b"a,1\n\xff,2\n";
+        line_producer(input, tx);
+    }
+}
@@ -1,8 +1,17 @@
 extern crate clap;
 extern crate chrono;
+extern crate chrono_tz;
 extern crate time;
 extern crate rand;
 extern crate pbr;
+extern crate flate2;
+extern crate xz2;
+extern crate bzip2;
+extern crate zip;
+extern crate rust_decimal;
+#[cfg(test)]
+#[macro_use]
+extern crate rust_decimal_macros;
 
 mod cliparser;
 mod fxconv;
@@ -12,19 +21,33 @@ mod formatter;
 mod settings;
 mod converter;
 mod grouper;
+mod writer;
+mod sorter;
+mod input;
+mod template;
 
 use std::fs::File;
 use std::thread;
+use std::sync::mpsc::channel;
 use std::process::exit;
 use std::panic;
 use std::io::prelude::*;
 
 use market::timeframe::TimeFrame;
-use fxconv::AskBidOption;
-use formatter::TickDescription;
+use market::instrument::Precision;
+use fxconv::Label;
+use template::Column;
+use fxconv::Delimiter;
+use fxconv::BarType;
+use fxconv::Fill;
+use fxconv::OutOfSession;
+use formatter::TickFormat;
 use cliparser::parse;
+use input::Input;
 
+use chrono::prelude::*;
 use pbr::ProgressBar;
+use rust_decimal::Decimal;
 
 fn main() {
     // setup error handling
@@ -34,12 +57,19 @@ fn main() {
     let phantom = thread::Builder::new().name("phantom_main".to_string()).spawn(move || {
         // parse and extract application settings (see --help)
         let matches = parse();
-        let time_frame: TimeFrame = settings::time_frame(&matches);
-        let mut output_file: File = settings::output_file(&matches);
-        let input_files: Vec<File> = settings::input_files(&matches);
-        let ask_bid: Option<AskBidOption> = settings::ask_bid(&matches);
+        let session = settings::session(&matches);
+        let time_frames: Vec<(String, TimeFrame)> = settings::time_frames(&matches, session.as_ref().map(|(session, _)| session));
+        let output_files: Vec<File> = settings::output_files(&matches, &time_frames);
+        let columns: Vec<Column> = settings::columns(&matches);
+        let label: Label = settings::label(&matches);
+        let bar_type: BarType = settings::bar_type(&matches);
+        let fill: Fill = settings::fill(&matches);
+        let precision: Option<Precision> = settings::precision(&matches);
+        let spread_pip_size: Option<Decimal> = settings::spread_pip_size(&matches);
         let headers: bool = settings::headers(&matches);
-        let tick: Vec<TickDescription> = settings::tick(&matches);
+        let out_delimiter: Delimiter = settings::out_delimiter(&matches);
+        let tick_format: TickFormat = settings::tick_format(&matches);
+        let input_files: Vec<Input> = sorter::sort(settings::input_files(&matches), &tick_format, settings::allow_overlap(&matches));
         let bar = settings::bar(&matches);
 
         let mut progress_files = ProgressBar::new(input_files.len() as u64);
@@ -50,71 +80,71 @@ fn main() {
         }
 
 
-        if headers {
-            let ask = "ask,ask,ask,ask";
-            let bid = "bid,bid,bid,bid";
-            let ohlc = "open,high,low,close";
-            let mut top = String::from(",");
-            let mut bottom = String::from("datetime,");
-            match ask_bid {
-                Some(AskBidOption::AskOnly) => {
-                    top.push_str(ask);
-                    bottom.push_str(ohlc);
-                },
-                Some(AskBidOption::BidOnly) => {
-                    top.push_str(ask);
-                    bottom.push_str(ohlc);
-                },
-                Some(AskBidOption::BidFirst) => {
-                    top.push_str(bid);
-                    top.push_str(",");
-                    top.push_str(ask);
-                    bottom.push_str(ohlc);
-                    bottom.push_str(",");
-                    bottom.push_str(ohlc);
-                },
-                _ => {
-                    top.push_str(ask);
-                    top.push_str(",");
-                    top.push_str(bid);
-                    bottom.push_str(ohlc);
-                    bottom.push_str(",");
-                    bottom.push_str(ohlc);
-                },
+        // a single chain for each timeframe groups the ticks of every input
+        // file so that bars continue across file boundaries
+        let mut txs_ticks = Vec::new();
+        let mut chains = Vec::new();
+        for ((_, time_frame), mut output_file) in time_frames.into_iter().zip(output_files) {
+            if headers {
+                let top: Vec<&str> = columns.iter().map(|column| column.header().0).collect();
+                let bottom: Vec<&str> = columns.iter().map(|column| column.header().1).collect();
+                let separator = out_delimiter.separator();
+                output_file.write_all(top.join(&separator).as_bytes()).expect("Cannot write to output");
+                output_file.write_all(b"\n").expect("Cannot write to output");
+                output_file.write_all(bottom.join(&separator).as_bytes()).expect("Cannot write to output");
+                output_file.write_all(b"\n").expect("Cannot write to output");
             }
-            output_file.write(top.as_bytes()).expect("Cannot write to output");
-            output_file.write(b"\n").expect("Cannot write to output");
-            output_file.write(bottom.as_bytes()).expect("Cannot write to output");
-            output_file.write(b"\n").expect("Cannot write to output");
+
+            let (tx_ticks, rx_ticks) = channel();
+            let (grouper, rx)   = grouper::create(rx_ticks, time_frame, bar_type, fill, session.as_ref().map(|(session, _)| session.clone()));
+            let (converter, rx) = converter::create(rx, columns.clone(), label.clone(), bar_type, precision, spread_pip_size, fill);
+            let writer = writer::create(rx, output_file, settings::output_zone(&matches), out_delimiter.clone(), columns.clone());
+            txs_ticks.push(tx_ticks);
+            chains.push((grouper, converter, writer));
         }
 
+        // the number of ticks outside of the session and the first of them
+        let mut out_of_session: (usize, Option<DateTime<Utc>>) = (0, None);
+
         // start the file reader / input data producer
-        for file in input_files.into_iter() {
-            let (line_producer, rx) = line_producer::create(file);
-            let (formatter, rx) = formatter::create(rx, tick.clone());
-            let (grouper, rx)   = grouper::create(rx, time_frame.clone());
-            let (converter, rx) = converter::create(rx, ask_bid.clone());
-
-            while let Some(mut row) = rx.recv().unwrap() {
-                let mut line: Vec<String> = Vec::new();
-                line.push(row.datetime.to_string());
-                for col in row.column_data.iter_mut() {
-                    line.push(col.to_string());
+        for input in input_files.into_iter() {
+            let (line_producer, rx) = line_producer::create(input);
+            let (formatter, rx) = formatter::create(rx, tick_format.clone());
+
+            // the ticks are parsed once and sent to the chain of every timeframe
+            while let Some(row) = rx.recv().unwrap() {
+                if let Some((ref session, action)) = session {
+                    if !session.is_open(row.datetime) {
+                        if action == OutOfSession::Exclude {
+                            continue;
+                        }
+                        out_of_session.0 += 1;
+                        out_of_session.1 = out_of_session.1.or(Some(row.datetime));
+                    }
+                }
+                for tx_ticks in &txs_ticks {
+                    tx_ticks.send(Some(row.clone())).expect("Could not send row data to the grouper");
                 }
-                let line = line.join(",");
-                let line = line.as_bytes();
-                output_file.write(line).expect("Could not write to file");
-                output_file.write(b"\n").expect("Could not write to file");
             }
 
             handle(line_producer);
             handle(formatter);
-            handle(grouper);
-            handle(converter);
             if bar {
                 progress_files.inc();
             }
         }
+        for tx_ticks in &txs_ticks {
+            tx_ticks.send(None).expect("Could not send None to the grouper");
+        }
+
+        for (grouper, converter, writer) in chains {
+            handle(grouper);
+            handle(converter);
+            handle(writer);
+        }
+        if let (count, Some(first)) = out_of_session {
+            eprintln!("Warning: {} ticks are outside of the trading session, the first at {}", count, first);
+        }
     });
     handle(phantom.expect("Thread did not spawn correctly"));
 }
@@ -137,6 +167,8 @@ fn sub_handle(t: thread::JoinHandle<()>) -> Option<String> {
         Err(e) => {
             if let Some(e) = e.downcast_ref::<&'static str>() {
                 Some(String::from(format!("Error: {}", e)))
+            } else if let Some(e) = e.downcast_ref::<String>() {
+                Some(format!("Error: {}", e))
             } else {
                 Some(String::from(format!("Unkown Error: {:?}", e)))
             }
@@ -156,4 +188,13 @@ mod tests {
         });
         assert_eq!(sub_handle(t), Some(String::from("Error: oops! I slipped..")));
     }
+
+    #[test]
+    fn formatted_error_from_thread() {
+        panic::set_hook(Box::new(|_info| {}));
+        let t = thread::spawn(move || {
+            panic!("oops! I slipped {} times..", 2);
+        });
+        assert_eq!(sub_handle(t), Some(String::from("Error: oops! I slipped 2 times..")));
+    }
 }
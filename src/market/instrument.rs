@@ -0,0 +1,109 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// How the prices of an instrument are written, every price is rounded to the
+/// nearest multiple of the tick size and written with a fixed number of digits
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Precision {
+    digits: u32,
+    tick_size: Decimal
+}
+
+impl Precision {
+    /// Prices with a number of decimals, the tick size is the last digit
+    pub fn new(digits: u32) -> Precision {
+        Precision { digits, tick_size: Decimal::new(1, digits) }
+    }
+
+    /// The default precision of a symbol, currency pairs are quoted with five
+    /// decimals except those quoted in yen (three). Symbols may be written as
+    /// "EURUSD", "EUR/USD" or "eur_usd".
+    pub fn for_symbol(symbol: &str) -> Option<Precision> {
+        let symbol = pair(symbol)?;
+        let digits = match (&symbol[..3], &symbol[3..]) {
+            ("XAU", _) => 2,
+            ("XAG", _) => 3,
+            (_, "JPY") | (_, "HUF") => 3,
+            _ => 5
+        };
+        Some(Precision::new(digits))
+    }
+
+    /// The size of a pip for a currency pair, 0.01 for pairs quoted in yen
+    /// or forint and 0.0001 for other pairs. Metals have no pips.
+    pub fn pip_size(symbol: &str) -> Option<Decimal> {
+        let symbol = pair(symbol)?;
+        match (&symbol[..3], &symbol[3..]) {
+            ("XAU", _) | ("XAG", _) | ("XPT", _) | ("XPD", _) => None,
+            (_, "JPY") | (_, "HUF") => Some(Decimal::new(1, 2)),
+            _ => Some(Decimal::new(1, 4))
+        }
+    }
+
+    /// The minimum price increment, it must not have more decimals than digits
+    pub fn with_tick_size(self, tick_size: Decimal) -> Precision {
+        Precision { tick_size, ..self }
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    /// The price rounded to the tick size (halves away from zero) with exactly
+    /// as many decimals as digits
+    pub fn round(&self, price: Decimal) -> Decimal {
+        let ticks = (price / self.tick_size).round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero);
+        let mut price = (ticks * self.tick_size).round_dp_with_strategy(self.digits, RoundingStrategy::MidpointAwayFromZero);
+        price.rescale(self.digits);
+        price
+    }
+}
+
+/// The six letters of a currency pair symbol in upper case, eg. "EURUSD" for
+/// "eur/usd"
+fn pair(symbol: &str) -> Option<String> {
+    let symbol: String = symbol.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if symbol.len() != 6 || !symbol.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols() {
+        assert_eq!(Precision::for_symbol("EURUSD"), Some(Precision::new(5)));
+        assert_eq!(Precision::for_symbol("usd/jpy"), Some(Precision::new(3)));
+        assert_eq!(Precision::for_symbol("XAU_USD"), Some(Precision::new(2)));
+        assert_eq!(Precision::for_symbol("US500"), None);
+        assert_eq!(Precision::pip_size("EURUSD"), Some(dec!(0.0001)));
+        assert_eq!(Precision::pip_size("EUR/JPY"), Some(dec!(0.01)));
+        assert_eq!(Precision::pip_size("XAUUSD"), None);
+        assert_eq!(Precision::pip_size("XAGUSD"), None);
+    }
+
+    #[test]
+    fn fixed_digits() {
+        let precision = Precision::new(5);
+        assert_eq!(precision.round(dec!(0.7654)).to_string(), "0.76540");
+        assert_eq!(precision.round(dec!(0.765412)).to_string(), "0.76541");
+        assert_eq!(precision.round(dec!(0.765415)).to_string(), "0.76542");
+        assert_eq!(Precision::new(3).round(dec!(104.8)).to_string(), "104.800");
+        assert_eq!(Precision::new(0).round(dec!(12345.5)).to_string(), "12346");
+    }
+
+    #[test]
+    fn tick_sizes() {
+        let precision = Precision::new(2).with_tick_size(dec!(0.25));
+        assert_eq!(precision.round(dec!(4512.1)).to_string(), "4512.00");
+        assert_eq!(precision.round(dec!(4512.13)).to_string(), "4512.25");
+        assert_eq!(precision.round(dec!(4512.9)).to_string(), "4513.00");
+        let precision = Precision::new(5).with_tick_size(dec!(0.00005));
+        assert_eq!(precision.round(dec!(1.123426)).to_string(), "1.12345");
+    }
+}
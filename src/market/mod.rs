@@ -1 +1,4 @@
 pub mod timeframe;
+pub mod timezone;
+pub mod instrument;
+pub mod session;
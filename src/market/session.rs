@@ -0,0 +1,144 @@
+use chrono::prelude::*;
+use time::Duration;
+use market::timezone::Zone;
+
+const WEEK_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// The trading hours of a market, open from a weekly open to a weekly close in
+/// the session zone. FX trades from Sunday 17:00 to Friday 17:00 in New York.
+/// Each trading day ends at the close time, so a holiday closes the market
+/// from the close time of the day before to the close time of the holiday.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    zone: Zone,
+    open: (Weekday, NaiveTime),
+    close: (Weekday, NaiveTime),
+    holidays: Vec<NaiveDate>
+}
+
+impl Session {
+    /// The FX session, Sunday 17:00 to Friday 17:00 America/New_York
+    pub fn fx() -> Session {
+        Session {
+            zone: Zone::parse("America/New_York").unwrap(),
+            open: (Weekday::Sun, NaiveTime::from_hms(17, 0, 0)),
+            close: (Weekday::Fri, NaiveTime::from_hms(17, 0, 0)),
+            holidays: Vec::new()
+        }
+    }
+
+    pub fn with_zone(mut self, zone: Zone) -> Session {
+        self.zone = zone;
+        self
+    }
+
+    pub fn with_open(mut self, weekday: Weekday, time: NaiveTime) -> Session {
+        self.open = (weekday, time);
+        self
+    }
+
+    pub fn with_close(mut self, weekday: Weekday, time: NaiveTime) -> Session {
+        self.close = (weekday, time);
+        self
+    }
+
+    pub fn with_holidays(mut self, holidays: Vec<NaiveDate>) -> Session {
+        self.holidays = holidays;
+        self
+    }
+
+    /// Whether the market is open at the datetime
+    pub fn is_open(&self, datetime: DateTime<Utc>) -> bool {
+        let local = self.zone.local(datetime);
+        let trading_day = if local.time() >= self.close.1 { local.date().succ() } else { local.date() };
+        if self.holidays.contains(&trading_day) {
+            return false;
+        }
+        let now = week_seconds(local.weekday(), local.time());
+        let open = week_seconds(self.open.0, self.open.1);
+        let close = week_seconds(self.close.0, self.close.1);
+        if open < close {
+            now >= open && now < close
+        } else {
+            now >= open || now < close
+        }
+    }
+
+    /// The session zone shifted so that the weekly open is midnight on Monday,
+    /// days, weeks and months aligned to it start at the open of the session
+    /// (eg. 17:00 in New York)
+    pub fn alignment_zone(&self) -> Zone {
+        let open = week_seconds(self.open.0, self.open.1);
+        self.zone.with_shift(Duration::seconds((WEEK_SECONDS - open) % WEEK_SECONDS))
+    }
+}
+
+/// The seconds from Monday midnight
+fn week_seconds(weekday: Weekday, time: NaiveTime) -> i64 {
+    weekday.num_days_from_monday() as i64 * 24 * 60 * 60 + time.num_seconds_from_midnight() as i64
+}
+
+/// Parses a weekday and time such as "Sun 17:00"
+pub fn parse_weekday_time(value: &str) -> Option<(Weekday, NaiveTime)> {
+    let mut parts = value.split_whitespace();
+    let weekday = parts.next()?.parse::<Weekday>().ok()?;
+    let time = NaiveTime::parse_from_str(parts.next()?, "%H:%M").ok()?;
+    match parts.next() {
+        Some(_) => None,
+        None => Some((weekday, time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fx_hours() {
+        let session = Session::fx();
+        // 2016-11-04 is a Friday, New York is UTC-4 until 2016-11-06
+        assert!(session.is_open(Utc.ymd(2016, 11, 4).and_hms(20, 59, 59)));
+        assert!(!session.is_open(Utc.ymd(2016, 11, 4).and_hms(21, 0, 0)));
+        assert!(!session.is_open(Utc.ymd(2016, 11, 5).and_hms(12, 0, 0)));
+        // and UTC-5 after, so Sunday 17:00 is 22:00 UTC
+        assert!(!session.is_open(Utc.ymd(2016, 11, 6).and_hms(21, 59, 59)));
+        assert!(session.is_open(Utc.ymd(2016, 11, 6).and_hms(22, 0, 0)));
+        assert!(session.is_open(Utc.ymd(2016, 11, 8).and_hms(3, 0, 0)));
+    }
+
+    #[test]
+    fn holidays() {
+        let session = Session::fx().with_holidays(vec![NaiveDate::from_ymd(2016, 12, 26)]);
+        // closed from 17:00 on the 23rd (Friday) to 17:00 on the 26th
+        assert!(!session.is_open(Utc.ymd(2016, 12, 25).and_hms(23, 0, 0)));
+        assert!(!session.is_open(Utc.ymd(2016, 12, 26).and_hms(21, 59, 59)));
+        assert!(session.is_open(Utc.ymd(2016, 12, 26).and_hms(22, 0, 0)));
+    }
+
+    #[test]
+    fn custom_hours() {
+        let session = Session::fx()
+            .with_zone(Zone::utc())
+            .with_open(Weekday::Mon, NaiveTime::from_hms(8, 0, 0))
+            .with_close(Weekday::Fri, NaiveTime::from_hms(16, 30, 0));
+        assert!(!session.is_open(Utc.ymd(2016, 11, 7).and_hms(7, 59, 0)));
+        assert!(session.is_open(Utc.ymd(2016, 11, 7).and_hms(8, 0, 0)));
+        assert!(session.is_open(Utc.ymd(2016, 11, 11).and_hms(16, 29, 0)));
+        assert!(!session.is_open(Utc.ymd(2016, 11, 11).and_hms(16, 30, 0)));
+    }
+
+    #[test]
+    fn alignment() {
+        assert_eq!(format!("{}", Session::fx().alignment_zone()), "America/New_York+07:00");
+        let session = Session::fx().with_zone(Zone::utc()).with_open(Weekday::Mon, NaiveTime::from_hms(0, 0, 0));
+        assert!(session.alignment_zone().is_utc());
+    }
+
+    #[test]
+    fn weekday_times() {
+        assert_eq!(parse_weekday_time("Sun 17:00"), Some((Weekday::Sun, NaiveTime::from_hms(17, 0, 0))));
+        assert_eq!(parse_weekday_time("friday 16:30"), Some((Weekday::Fri, NaiveTime::from_hms(16, 30, 0))));
+        assert_eq!(parse_weekday_time("Sun"), None);
+        assert_eq!(parse_weekday_time("17:00 Sun"), None);
+    }
+}
@@ -1,36 +1,64 @@
 use std::fmt;
-
+use chrono::prelude::*;
+use time::Duration;
+use market::timezone::Zone;
 
 #[derive(PartialEq, Clone)]
 pub enum TimeUnit {
+    Nanosecond,
+    Microsecond,
+    Millisecond,
     Second,
     Minute,
     Hour,
     Day,
-    Week
+    Week,
+    Month,
+    Year
 }
 
 /// Display the TimeUnit in human readable terms.
 impl fmt::Display for TimeUnit {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", match *self {
+            TimeUnit::Nanosecond => "nanosecond",
+            TimeUnit::Microsecond => "microsecond",
+            TimeUnit::Millisecond => "millisecond",
             TimeUnit::Second => "second",
             TimeUnit::Minute => "minute",
             TimeUnit::Hour => "hour",
             TimeUnit::Day => "day",
-            TimeUnit::Week => "week"
+            TimeUnit::Week => "week",
+            TimeUnit::Month => "month",
+            TimeUnit::Year => "year"
         })
     }
 }
 #[derive(Clone)]
 pub struct TimeFrame {
     unit: TimeUnit,
-    length: usize
+    length: usize,
+    offset: Duration,
+    zone: Zone
 }
 
 impl TimeFrame {
     pub fn new(length: usize, unit: TimeUnit) -> TimeFrame {
-        TimeFrame { unit: unit, length: length }
+        TimeFrame { unit: unit, length: length, offset: Duration::zero(), zone: Zone::utc() }
+    }
+
+    /// Aligns the timeframe boundaries to the clock of the zone, so that daily
+    /// bars start at the zone's midnight whatever its daylight saving.
+    pub fn with_zone(mut self, zone: Zone) -> TimeFrame {
+        self.zone = zone;
+        self
+    }
+
+    /// Shifts the timeframe boundaries away from the clock (eg. 4h bars that
+    /// start at 01:00 rather than 00:00).
+    pub fn with_offset(mut self, offset: Duration) -> TimeFrame {
+        self.offset = offset;
+        self
     }
 
     pub fn unit(&self) -> &TimeUnit {
@@ -40,6 +68,156 @@ impl TimeFrame {
     pub fn len(&self) -> usize {
         self.length
     }
+
+    /// The fixed length of the timeframe, months and years have none.
+    pub fn duration(&self) -> Option<Duration> {
+        let length = self.length as i64;
+        match self.unit {
+            TimeUnit::Nanosecond => Some(Duration::nanoseconds(length)),
+            TimeUnit::Microsecond => Some(Duration::microseconds(length)),
+            TimeUnit::Millisecond => Some(Duration::milliseconds(length)),
+            TimeUnit::Second => Some(Duration::seconds(length)),
+            TimeUnit::Minute => Some(Duration::minutes(length)),
+            TimeUnit::Hour => Some(Duration::hours(length)),
+            TimeUnit::Day => Some(Duration::days(length)),
+            TimeUnit::Week => Some(Duration::weeks(length)),
+            TimeUnit::Month => None,
+            TimeUnit::Year => None
+        }
+    }
+
+    /// Returns the datetime that is `count` timeframes after `datetime`. Months
+    /// and years have no fixed length so they are stepped on the calendar, with
+    /// the day clamped to the end of shorter months (Jan 31 + 1 month = Feb 28).
+    pub fn after(&self, datetime: DateTime<Utc>, count: i64) -> DateTime<Utc> {
+        match self.unit {
+            TimeUnit::Month => add_months(datetime, self.length as i64 * count),
+            TimeUnit::Year => add_months(datetime, self.length as i64 * count * 12),
+            _ => datetime + self.duration().unwrap() * count as i32
+        }
+    }
+
+    /// The start of the timeframe that contains the datetime.
+    pub fn open_of(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        let duration = match self.sub_day_duration() {
+            Some(duration) => duration,
+            None => {
+                let local = self.local(datetime);
+                return self.zone.instant((self.align(local - self.offset) + self.offset).naive_utc());
+            }
+        };
+        let offset = self.offset_at(datetime);
+        let open = self.clock_open(datetime, offset);
+        if self.offset_at(open) == offset {
+            return open;
+        }
+        // the clock changed (daylight saving) after the open, which is on the
+        // clock of the earlier offset
+        let earlier = self.offset_at(open);
+        let mut open = self.clock_open(datetime, earlier);
+        while self.offset_at(open) != earlier {
+            open = open - duration;
+        }
+        open
+    }
+
+    /// The end of the timeframe that contains the datetime, which is also the
+    /// start of the following timeframe.
+    pub fn close_of(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        let duration = match self.sub_day_duration() {
+            Some(duration) => duration,
+            None => {
+                let local = self.local(datetime);
+                return self.zone.instant((self.after(self.align(local - self.offset), 1) + self.offset).naive_utc());
+            }
+        };
+        let offset = self.offset_at(datetime);
+        let close = self.clock_open(datetime, offset) + duration;
+        if self.offset_at(close) == offset {
+            return close;
+        }
+        // the clock changes (daylight saving) before the close, which is on the
+        // clock of the later offset
+        let later = self.offset_at(close);
+        let mut close = self.clock_open(datetime, later) + duration;
+        while close <= datetime || self.offset_at(close) != later {
+            close = close + duration;
+        }
+        close
+    }
+
+    /// The length of timeframes shorter than a day. Their boundaries are found
+    /// on the instant rather than the wall clock, so that the hour repeated when
+    /// daylight saving ends is a bar of its own.
+    fn sub_day_duration(&self) -> Option<Duration> {
+        match self.unit {
+            TimeUnit::Day | TimeUnit::Week | TimeUnit::Month | TimeUnit::Year => None,
+            _ => self.duration()
+        }
+    }
+
+    /// The offset of the zone from UTC at the datetime
+    fn offset_at(&self, datetime: DateTime<Utc>) -> Duration {
+        self.local(datetime).signed_duration_since(datetime)
+    }
+
+    /// The start of the timeframe that contains the datetime on the clock of a
+    /// fixed offset from UTC
+    fn clock_open(&self, datetime: DateTime<Utc>, offset: Duration) -> DateTime<Utc> {
+        self.align(datetime + offset - self.offset) + self.offset - offset
+    }
+
+    /// The wall clock of the zone, held as UTC so that the clock arithmetic of
+    /// the timeframe is free of daylight saving
+    fn local(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&self.zone.local(datetime))
+    }
+
+    /// Rounds the datetime down to the clock. Timeframes up to a day long are
+    /// counted from the unix epoch, weeks start on Monday, months are counted
+    /// from January and years from year zero.
+    fn align(&self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        match self.unit {
+            TimeUnit::Month => {
+                let total = datetime.year() as i64 * 12 + datetime.month0() as i64;
+                let total = total - total.rem_euclid(self.length as i64);
+                Utc.ymd(total.div_euclid(12) as i32, total.rem_euclid(12) as u32 + 1, 1).and_hms(0, 0, 0)
+            },
+            TimeUnit::Year => {
+                let year = datetime.year() - datetime.year().rem_euclid(self.length as i32);
+                Utc.ymd(year, 1, 1).and_hms(0, 0, 0)
+            },
+            _ => {
+                // the epoch is a Thursday, the first Monday is four days later
+                let origin = match self.unit {
+                    TimeUnit::Week => Duration::days(4).num_nanoseconds().unwrap(),
+                    _ => 0
+                };
+                let length = self.duration().unwrap().num_nanoseconds().expect("Timeframe is too long");
+                let nanos = datetime.timestamp_nanos() - origin;
+                Utc.timestamp_nanos(nanos - nanos.rem_euclid(length) + origin)
+            }
+        }
+    }
+}
+
+/// Number of days in the given month of the given year.
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = if month == 12 {
+        NaiveDate::from_ymd(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(year, month + 1, 1)
+    };
+    next.pred().day()
+}
+
+/// Adds calendar months to the datetime, keeping the time of day.
+fn add_months(datetime: DateTime<Utc>, months: i64) -> DateTime<Utc> {
+    let total = datetime.year() as i64 * 12 + datetime.month0() as i64 + months;
+    let year = total.div_euclid(12) as i32;
+    let month = total.rem_euclid(12) as u32 + 1;
+    let day = datetime.day().min(days_in_month(year, month));
+    Utc.ymd(year, month, day).and_time(datetime.time()).expect("Invalid calendar datetime")
 }
 
 impl fmt::Display for TimeFrame {
@@ -54,11 +232,16 @@ mod tests {
 
     #[test]
     fn timeunit_formatting() {
+        assert_eq!("nanosecond", format!("{}", TimeUnit::Nanosecond));
+        assert_eq!("microsecond", format!("{}", TimeUnit::Microsecond));
+        assert_eq!("millisecond", format!("{}", TimeUnit::Millisecond));
         assert_eq!("second", format!("{}", TimeUnit::Second));
         assert_eq!("minute", format!("{}", TimeUnit::Minute));
         assert_eq!("hour", format!("{}", TimeUnit::Hour));
         assert_eq!("day", format!("{}", TimeUnit::Day));
         assert_eq!("week", format!("{}", TimeUnit::Week));
+        assert_eq!("month", format!("{}", TimeUnit::Month));
+        assert_eq!("year", format!("{}", TimeUnit::Year));
     }
 
     #[test]
@@ -72,4 +255,111 @@ mod tests {
         let tf = TimeFrame::new(45, TimeUnit::Minute);
         assert!(*tf.unit() == TimeUnit::Minute);
     }
+
+    #[test]
+    fn timeframe_after_fixed_length() {
+        let tf = TimeFrame::new(15, TimeUnit::Minute);
+        let dt = Utc.ymd(2016, 11, 1).and_hms(22, 50, 0);
+        assert_eq!(tf.after(dt, 1), Utc.ymd(2016, 11, 1).and_hms(23, 5, 0));
+        assert_eq!(tf.after(dt, 4), Utc.ymd(2016, 11, 1).and_hms(23, 50, 0));
+    }
+
+    #[test]
+    fn timeframe_after_month() {
+        let tf = TimeFrame::new(1, TimeUnit::Month);
+        let dt = Utc.ymd(2016, 1, 31).and_hms(12, 0, 0);
+        assert_eq!(tf.after(dt, 1), Utc.ymd(2016, 2, 29).and_hms(12, 0, 0));
+        assert_eq!(tf.after(dt, 2), Utc.ymd(2016, 3, 31).and_hms(12, 0, 0));
+        assert_eq!(tf.after(dt, 11), Utc.ymd(2016, 12, 31).and_hms(12, 0, 0));
+        assert_eq!(tf.after(dt, 13), Utc.ymd(2017, 2, 28).and_hms(12, 0, 0));
+    }
+
+    #[test]
+    fn timeframe_after_year() {
+        let tf = TimeFrame::new(2, TimeUnit::Year);
+        let dt = Utc.ymd(2016, 2, 29).and_hms(0, 0, 0);
+        assert_eq!(tf.after(dt, 1), Utc.ymd(2018, 2, 28).and_hms(0, 0, 0));
+        assert_eq!(tf.after(dt, 2), Utc.ymd(2020, 2, 29).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn timeframe_aligned_to_clock() {
+        let tf = TimeFrame::new(4, TimeUnit::Hour);
+        let dt = Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 11, 1).and_hms(20, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0));
+        let tf = TimeFrame::new(1, TimeUnit::Day);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 11, 1).and_hms(0, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 2).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn timeframe_sub_second() {
+        let tf = TimeFrame::new(250, TimeUnit::Millisecond);
+        let dt = Utc.ymd(2016, 11, 1).and_hms_micro(22, 30, 3, 617254);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 500));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 750));
+        let tf = TimeFrame::new(100, TimeUnit::Microsecond);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 11, 1).and_hms_micro(22, 30, 3, 617200));
+        let tf = TimeFrame::new(10, TimeUnit::Nanosecond);
+        let dt = Utc.ymd(2016, 11, 1).and_hms_nano(22, 30, 3, 617254093);
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 1).and_hms_nano(22, 30, 3, 617254100));
+    }
+
+    #[test]
+    fn timeframe_aligned_to_calendar() {
+        // 2016-11-01 is a Tuesday
+        let dt = Utc.ymd(2016, 11, 1).and_hms(22, 30, 3);
+        let tf = TimeFrame::new(1, TimeUnit::Week);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 10, 31).and_hms(0, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 7).and_hms(0, 0, 0));
+        let tf = TimeFrame::new(3, TimeUnit::Month);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 10, 1).and_hms(0, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2017, 1, 1).and_hms(0, 0, 0));
+        let tf = TimeFrame::new(1, TimeUnit::Year);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 1, 1).and_hms(0, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2017, 1, 1).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn timeframe_with_offset() {
+        let tf = TimeFrame::new(4, TimeUnit::Hour).with_offset(Duration::hours(1));
+        let dt = Utc.ymd(2016, 11, 1).and_hms(0, 30, 0);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 10, 31).and_hms(21, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 1).and_hms(1, 0, 0));
+        let tf = TimeFrame::new(1, TimeUnit::Month).with_offset(Duration::hours(-7));
+        let dt = Utc.ymd(2016, 10, 31).and_hms(18, 0, 0);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 10, 31).and_hms(17, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 30).and_hms(17, 0, 0));
+    }
+
+    #[test]
+    fn timeframe_with_zone() {
+        let zone = Zone::parse("America/New_York").unwrap();
+        // midnight in New York is 04:00 UTC in summer and 05:00 UTC in winter
+        let tf = TimeFrame::new(1, TimeUnit::Day).with_zone(zone);
+        let dt = Utc.ymd(2016, 11, 6).and_hms(12, 0, 0);
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 11, 6).and_hms(4, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 7).and_hms(5, 0, 0));
+        // the New York close, 17:00 in New York
+        let tf = TimeFrame::new(1, TimeUnit::Day).with_zone(Zone::parse("America/New_York+07:00").unwrap());
+        assert_eq!(tf.open_of(dt), Utc.ymd(2016, 11, 5).and_hms(21, 0, 0));
+        assert_eq!(tf.close_of(dt), Utc.ymd(2016, 11, 6).and_hms(22, 0, 0));
+    }
+
+    #[test]
+    fn timeframe_across_daylight_saving() {
+        let zone = Zone::parse("America/New_York").unwrap();
+        // 4 hour bars hold the repeated hour when daylight saving ends (06:00 UTC)
+        let tf = TimeFrame::new(4, TimeUnit::Hour).with_zone(zone);
+        for dt in &[Utc.ymd(2016, 11, 6).and_hms(5, 30, 0), Utc.ymd(2016, 11, 6).and_hms(8, 30, 0)] {
+            assert_eq!(tf.open_of(*dt), Utc.ymd(2016, 11, 6).and_hms(4, 0, 0));
+            assert_eq!(tf.close_of(*dt), Utc.ymd(2016, 11, 6).and_hms(9, 0, 0));
+        }
+        // and lose the skipped hour when it starts (07:00 UTC)
+        for dt in &[Utc.ymd(2016, 3, 13).and_hms(6, 30, 0), Utc.ymd(2016, 3, 13).and_hms(7, 30, 0)] {
+            assert_eq!(tf.open_of(*dt), Utc.ymd(2016, 3, 13).and_hms(5, 0, 0));
+            assert_eq!(tf.close_of(*dt), Utc.ymd(2016, 3, 13).and_hms(8, 0, 0));
+        }
+    }
 }
@@ -0,0 +1,152 @@
+use std::fmt;
+use chrono::prelude::*;
+use chrono::LocalResult;
+use chrono_tz::Tz;
+use time::Duration;
+
+/// A time zone from the tz database, optionally shifted by a fixed amount.
+/// Shifted zones describe conventions such as "New York close" data, which is
+/// America/New_York moved forward seven hours (GMT+2 in winter, GMT+3 in
+/// summer) so that the trading day ends at midnight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Zone {
+    tz: Tz,
+    shift: Duration
+}
+
+impl Zone {
+    pub fn utc() -> Zone {
+        Zone { tz: Tz::UTC, shift: Duration::zero() }
+    }
+
+    /// Parses "UTC", a tz database name such as "America/New_York", a fixed
+    /// offset such as "+02:00" or a shifted zone such as "America/New_York+07:00".
+    pub fn parse(zone: &str) -> Option<Zone> {
+        if let Ok(tz) = zone.parse::<Tz>() {
+            return Some(Zone { tz, shift: Duration::zero() });
+        }
+        let split = zone.rfind(['+', '-'])?;
+        let tz = if split == 0 { Tz::UTC } else { zone[..split].parse::<Tz>().ok()? };
+        let shift = parse_offset(&zone[split..])?;
+        Some(Zone { tz, shift })
+    }
+
+    /// The zone moved by a further fixed amount
+    pub fn with_shift(self, shift: Duration) -> Zone {
+        Zone { shift: self.shift + shift, ..self }
+    }
+
+    pub fn is_utc(&self) -> bool {
+        self.tz == Tz::UTC && self.shift == Duration::zero()
+    }
+
+    /// The wall clock time in the zone
+    pub fn local(&self, datetime: DateTime<Utc>) -> NaiveDateTime {
+        datetime.with_timezone(&self.tz).naive_local() + self.shift
+    }
+
+    /// The instant of a wall clock time in the zone. Times that happen twice
+    /// when daylight saving ends are taken the first time, times skipped when
+    /// daylight saving starts are moved past the gap.
+    pub fn instant(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let mut local = local - self.shift;
+        loop {
+            match self.tz.from_local_datetime(&local) {
+                LocalResult::Single(datetime) => return datetime.with_timezone(&Utc),
+                LocalResult::Ambiguous(earliest, _) => return earliest.with_timezone(&Utc),
+                LocalResult::None => local += Duration::minutes(15)
+            }
+        }
+    }
+
+    /// The datetime as written to the output, UTC keeps its " UTC" suffix and
+    /// other zones are written with their offset (eg. "-04:00")
+    pub fn display(&self, datetime: DateTime<Utc>) -> String {
+        if self.is_utc() {
+            return datetime.to_string();
+        }
+        let local = self.local(datetime);
+        let offset = FixedOffset::east(local.signed_duration_since(datetime.naive_utc()).num_seconds() as i32);
+        offset.from_utc_datetime(&datetime.naive_utc()).to_string()
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tz.name())?;
+        if self.shift != Duration::zero() {
+            let minutes = self.shift.num_minutes();
+            let sign = if minutes < 0 { '-' } else { '+' };
+            write!(f, "{}{:02}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses "+02:00", "+0200", "+02" or "-7"
+fn parse_offset(offset: &str) -> Option<Duration> {
+    let sign = match offset.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None
+    };
+    let digits: String = offset[1..].chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hours, minutes) = if digits.len() > 2 {
+        (digits[..digits.len() - 2].parse::<i64>().ok()?, digits[digits.len() - 2..].parse::<i64>().ok()?)
+    } else {
+        (digits.parse::<i64>().ok()?, 0)
+    };
+    if minutes >= 60 {
+        return None;
+    }
+    Some(Duration::minutes(sign * (hours * 60 + minutes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_zones() {
+        assert!(Zone::parse("UTC").unwrap().is_utc());
+        assert_eq!(format!("{}", Zone::parse("America/New_York").unwrap()), "America/New_York");
+        assert_eq!(format!("{}", Zone::parse("+02:00").unwrap()), "UTC+02:00");
+        assert_eq!(format!("{}", Zone::parse("-0530").unwrap()), "UTC-05:30");
+        assert_eq!(format!("{}", Zone::parse("America/New_York+7").unwrap()), "America/New_York+07:00");
+        assert_eq!(format!("{}", Zone::parse("America/Port-au-Prince").unwrap()), "America/Port-au-Prince");
+        assert_eq!(Zone::parse("Mars/Olympus"), None);
+        assert_eq!(Zone::parse("+02:75"), None);
+    }
+
+    #[test]
+    fn daylight_saving() {
+        let zone = Zone::parse("America/New_York").unwrap();
+        // EDT in summer, EST in winter
+        let summer = Utc.ymd(2016, 7, 1).and_hms(12, 0, 0);
+        let winter = Utc.ymd(2016, 12, 1).and_hms(12, 0, 0);
+        assert_eq!(zone.local(summer), NaiveDate::from_ymd(2016, 7, 1).and_hms(8, 0, 0));
+        assert_eq!(zone.local(winter), NaiveDate::from_ymd(2016, 12, 1).and_hms(7, 0, 0));
+        assert_eq!(zone.instant(NaiveDate::from_ymd(2016, 7, 1).and_hms(8, 0, 0)), summer);
+        // 01:30 happens twice on 2016-11-06, 02:30 is skipped on 2016-03-13
+        assert_eq!(zone.instant(NaiveDate::from_ymd(2016, 11, 6).and_hms(1, 30, 0)), Utc.ymd(2016, 11, 6).and_hms(5, 30, 0));
+        assert_eq!(zone.instant(NaiveDate::from_ymd(2016, 3, 13).and_hms(2, 30, 0)), Utc.ymd(2016, 3, 13).and_hms(7, 0, 0));
+    }
+
+    #[test]
+    fn new_york_close() {
+        let zone = Zone::parse("America/New_York+07:00").unwrap();
+        // 17:00 in New York is midnight, GMT+3 in summer and GMT+2 in winter
+        assert_eq!(zone.local(Utc.ymd(2016, 7, 1).and_hms(21, 0, 0)), NaiveDate::from_ymd(2016, 7, 2).and_hms(0, 0, 0));
+        assert_eq!(zone.local(Utc.ymd(2016, 12, 1).and_hms(22, 0, 0)), NaiveDate::from_ymd(2016, 12, 2).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn display() {
+        let datetime = Utc.ymd(2016, 11, 1).and_hms_milli(22, 30, 3, 617);
+        assert_eq!(Zone::utc().display(datetime), "2016-11-01 22:30:03.617 UTC");
+        assert_eq!(Zone::parse("America/New_York").unwrap().display(datetime), "2016-11-01 18:30:03.617 -04:00");
+    }
+}
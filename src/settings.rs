@@ -1,51 +1,128 @@
 use std::fs::File;
+use std::io::Read;
 use std::process::exit;
 use std::path::Path;
 use std::io;
 use std::fs::OpenOptions;
+use std::str::FromStr;
 
 use clap::ArgMatches;
+use time::Duration;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::Zero;
 
 use market::timeframe::TimeFrame;
 use market::timeframe::TimeUnit;
+use market::timezone::Zone;
+use market::instrument::Precision;
+use market::session;
+use market::session::Session;
 use fxconv::AskBidOption;
+use fxconv::AskBid;
+use fxconv::Statistic;
+use template;
+use template::{Aggregate, Column};
+use fxconv::Label;
+use fxconv::ExtraColumn;
+use fxconv::BarType;
+use fxconv::Fill;
+use fxconv::OutOfSession;
+use fxconv::Delimiter;
 use formatter::TickDescription;
+use formatter::DateTimeFormat;
+use formatter::EpochUnit;
+use formatter::TickFormat;
+use formatter::Header;
+use formatter::ColumnNames;
+use input;
+use input::Input;
 
-pub fn time_frame(matches: &ArgMatches) -> TimeFrame {
-    let tf = matches.value_of("timeframe").unwrap();
-    // count the digits
-    let mut digits = 0;
-    let mut unit: Option<char> = None;
-    for c in tf.chars() {
-        if c.is_digit(10) {
-            digits += 1;
-        }
-        else {
-            unit = Some(c);
-            break;
+/// The timeframes of the bars, with each as it is written in TIMEFRAME
+pub fn time_frames(matches: &ArgMatches, session: Option<&Session>) -> Vec<(String, TimeFrame)> {
+    let mut time_frames: Vec<(String, TimeFrame)> = Vec::new();
+    for tf in matches.value_of("timeframe").unwrap().split(',') {
+        let tf = tf.trim();
+        if time_frames.iter().any(|(name, _)| name == tf) {
+            panic!("TIMEFRAME lists '{}' more than once", tf);
         }
+        time_frames.push((String::from(tf), time_frame(matches, tf, session)));
+    }
+    time_frames
+}
+
+/// The timeframe of the bars. With a trading session, bars of a day or longer
+/// start at the open of the session rather than at midnight.
+fn time_frame(matches: &ArgMatches, tf: &str, session: Option<&Session>) -> TimeFrame {
+    let tf = parse_time_frame(tf).with_offset(offset(matches));
+    match (session, tf.unit()) {
+        (Some(session), TimeUnit::Day) | (Some(session), TimeUnit::Week) |
+        (Some(session), TimeUnit::Month) | (Some(session), TimeUnit::Year) =>
+            tf.with_zone(session.alignment_zone()),
+        _ => tf.with_zone(output_zone(matches))
     }
-    // check the string only has one non-digit char
-    if tf.len() != digits + 1 {
+}
+
+/// Parses a timeframe such as "15m" (see ARGS/TIMEFRAME in --help)
+fn parse_time_frame(tf: &str) -> TimeFrame {
+    // count the digits
+    let digits = tf.chars().take_while(|c| c.is_ascii_digit()).count();
+    // check there is a number followed by a unit
+    if digits == 0 || digits == tf.len() {
         let e = format!("Timeframe is incorrectly formatted: '{}'", tf);
         panic!(e);
     }
     // Set up time frame variables
     let length = tf[0..digits].to_string().parse::<usize>().unwrap();
-    let unit = match unit {
-        Some('s') => TimeUnit::Second,
-        Some('m') => TimeUnit::Minute,
-        Some('h') => TimeUnit::Hour,
-        Some('d') => TimeUnit::Day,
-        Some('w') => TimeUnit::Week,
+    if length == 0 {
+        panic!("Timeframe must be longer than zero: '{}'", tf);
+    }
+    let unit = match &tf[digits..] {
+        "ns" => TimeUnit::Nanosecond,
+        "us" => TimeUnit::Microsecond,
+        "ms" => TimeUnit::Millisecond,
+        "s" => TimeUnit::Second,
+        "m" => TimeUnit::Minute,
+        "h" => TimeUnit::Hour,
+        "d" => TimeUnit::Day,
+        "w" => TimeUnit::Week,
+        "n" => TimeUnit::Month,
+        "y" => TimeUnit::Year,
         _ => panic!("Unit not valid, see ARGS/TIMEFRAME in --help")
     };
     // Set time_frame
     TimeFrame::new( length, unit )
 }
 
-pub fn output_file(matches: &ArgMatches) -> File {
-    let name = matches.value_of("output").unwrap();
+/// The shift of the timeframe boundaries from the clock, eg. "1h" or "-7h".
+pub fn offset(matches: &ArgMatches) -> Duration {
+    match matches.value_of("offset") {
+        Some(offset) => {
+            let (sign, tf) = match offset.strip_prefix('-') {
+                Some(tf) => (-1, tf),
+                None => (1, offset)
+            };
+            let tf = parse_time_frame(tf);
+            match tf.duration() {
+                Some(duration) => duration * sign,
+                None => panic!("--offset must be a fixed length of time, not {} {}(s)", tf.len(), tf.unit())
+            }
+        },
+        None => Duration::zero()
+    }
+}
+
+/// The output file of each timeframe, named by replacing "{timeframe}" in
+/// OUTPUT
+pub fn output_files(matches: &ArgMatches, time_frames: &[(String, TimeFrame)]) -> Vec<File> {
+    let template = matches.value_of("output").unwrap();
+    if time_frames.len() > 1 && !template.contains("{timeframe}") {
+        panic!("OUTPUT must contain {{timeframe}} when there is more than one timeframe: '{}'", template);
+    }
+    time_frames.iter().map(|(tf, _)| output_file(matches, &template.replace("{timeframe}", tf))).collect()
+}
+
+fn output_file(matches: &ArgMatches, name: &str) -> File {
     let path = Path::new(name);
     //check if dir
     if path.file_name() == Option::None {
@@ -93,14 +170,17 @@ pub fn output_file(matches: &ArgMatches) -> File {
     OpenOptions::new().create(true).write(true).open(name).expect(&format!("Could not open output file '{}'", name))
 }
 
-pub fn input_files(matches: &ArgMatches) -> Vec<File> {
+pub fn input_files(matches: &ArgMatches) -> Vec<Input> {
     let input_names: Vec<String> = matches.values_of("inputs").unwrap().map(String::from).collect();
-    let mut files: Vec<File> = Vec::new();
+    let mut inputs: Vec<Input> = Vec::new();
     for name in input_names.into_iter() {
-        let file = File::open(name.as_str()).expect(&format!("Could not open output file '{}'", name));
-        files.push(file);
+        inputs.extend(input::open(name.as_str()));
     }
-    files
+    inputs
+}
+
+pub fn allow_overlap(matches: &ArgMatches) -> bool {
+    matches.is_present("allow-overlap")
 }
 
 pub fn ask_bid(matches: &ArgMatches) -> Option<AskBidOption> {
@@ -120,6 +200,294 @@ pub fn ask_bid(matches: &ArgMatches) -> Option<AskBidOption> {
     }
 }
 
+/// The groups of OHLC columns in the order they are written. With --mid the
+/// mid columns come after any ask and bid columns that are asked for.
+pub fn price_groups(matches: &ArgMatches) -> Vec<AskBid> {
+    let mid = matches.is_present("mid");
+    let mut groups = match ask_bid(matches) {
+        Some(AskBidOption::AskOnly) => vec![AskBid::Ask],
+        Some(AskBidOption::BidOnly) => vec![AskBid::Bid],
+        Some(AskBidOption::BidFirst) => vec![AskBid::Bid, AskBid::Ask],
+        Some(AskBidOption::AskFirst) => vec![AskBid::Ask, AskBid::Bid],
+        None if mid => vec![],
+        None => vec![AskBid::Ask, AskBid::Bid]
+    };
+    if mid {
+        groups.push(AskBid::Mid);
+    }
+    groups
+}
+
+/// The statistics written after the OHLC columns of each price group. A
+/// statistic is for every price group unless it names one, eg. "ask:vwap".
+pub fn statistics(matches: &ArgMatches) -> Vec<(AskBid, Statistic)> {
+    let groups = price_groups(matches);
+    let mut statistics: Vec<(AskBid, Statistic)> = Vec::new();
+    if let Some(values) = matches.values_of("stats") {
+        for value in values {
+            let (sides, name) = match value.find(':') {
+                Some(split) => {
+                    let side = match &value[..split] {
+                        "ask" => AskBid::Ask,
+                        "bid" => AskBid::Bid,
+                        "mid" => AskBid::Mid,
+                        side => panic!("--stats side must be ask, bid or mid, not '{}'", side)
+                    };
+                    if !groups.contains(&side) {
+                        panic!("--stats '{}' is for {} prices which are not written", value, &value[..split]);
+                    }
+                    (vec![side], &value[split + 1..])
+                },
+                None => (groups.clone(), value)
+            };
+            let statistic = match name {
+                "twap" => Statistic::Twap,
+                "vwap" => Statistic::Vwap,
+                "mean" => Statistic::Mean,
+                "median" => Statistic::Median,
+                "stddev" => Statistic::StdDev,
+                _ => panic!("--stats contains invalid statistic: '{}'", name)
+            };
+            for side in sides {
+                statistics.push((side, statistic));
+            }
+        }
+    }
+    statistics
+}
+
+pub fn label(matches: &ArgMatches) -> Label {
+    match matches.value_of("label") {
+        Some("open") => Label::Open,
+        Some("close") => Label::Close,
+        Some("last-tick") => Label::LastTick,
+        _ => Label::FirstTick
+    }
+}
+
+/// The precision of output prices, --digits and --tick-size override the
+/// defaults of --symbol. Without any of them prices keep their input digits.
+pub fn precision(matches: &ArgMatches) -> Option<Precision> {
+    let tick_size = matches.value_of("tick-size").map(|tick_size| match Decimal::from_str(tick_size) {
+        Ok(tick_size) if tick_size > Decimal::zero() => tick_size.normalize(),
+        _ => panic!("--tick-size must be a positive number: '{}'", tick_size)
+    });
+    let precision = match matches.value_of("digits") {
+        Some(digits) => match digits.parse::<u32>() {
+            Ok(digits) if digits <= 16 => Some(Precision::new(digits)),
+            _ => panic!("--digits must be a number from 0 to 16: '{}'", digits)
+        },
+        None => match matches.value_of("symbol") {
+            Some(symbol) => match Precision::for_symbol(symbol) {
+                Some(precision) => Some(precision),
+                None => panic!("No default precision for symbol '{}', use --digits", symbol)
+            },
+            None => tick_size.map(|tick_size| Precision::new(tick_size.scale()))
+        }
+    };
+    match (precision, tick_size) {
+        (Some(precision), Some(tick_size)) => {
+            if tick_size.scale() > precision.digits() {
+                panic!("--tick-size {} has more decimals than the {} digits written", tick_size, precision.digits());
+            }
+            Some(precision.with_tick_size(tick_size))
+        },
+        (precision, _) => precision
+    }
+}
+
+/// The output columns, from the --columns template or else from the options
+/// it replaces. Volumes must be read from the input with the volume codes of
+/// --tick, or be columns of the --bars.
+pub fn columns(matches: &ArgMatches) -> Vec<Column> {
+    let columns = match matches.value_of("columns") {
+        Some(columns) => template::parse(columns).unwrap_or_else(|e| panic!("--columns contains {}", e)),
+        None => template::shorthand(&price_groups(matches), &statistics(matches), &extra_columns(matches))
+    };
+    if let Some(bars) = bar_columns(matches) {
+        for column in &columns {
+            let resampled = match *column {
+                Column::DateTime => true,
+                Column::Price(_, Aggregate::Statistic(_)) => false,
+                Column::Price(side, _) => bars.contains(&Column::Price(side, Aggregate::Open)),
+                Column::Extra(ExtraColumn::Volume) => bars.contains(column) ||
+                    (bars.contains(&Column::Extra(ExtraColumn::AskVolume)) && bars.contains(&Column::Extra(ExtraColumn::BidVolume))),
+                Column::Extra(ExtraColumn::AskVolume) | Column::Extra(ExtraColumn::BidVolume) |
+                Column::Extra(ExtraColumn::TickCount) => bars.contains(column),
+                Column::Extra(_) => false
+            };
+            if !resampled {
+                panic!("The {} column cannot be resampled from the --bars columns", column.name());
+            }
+        }
+        return columns;
+    }
+    let tick = tick(matches);
+    for column in &columns {
+        let (needs, codes): (&[TickDescription], &str) = match *column {
+            Column::Extra(ExtraColumn::AskVolume) => (&[TickDescription::AskVolume], "'A'"),
+            Column::Extra(ExtraColumn::BidVolume) => (&[TickDescription::BidVolume], "'B'"),
+            Column::Extra(ExtraColumn::Volume) | Column::Price(_, Aggregate::Statistic(Statistic::Vwap)) =>
+                (&[TickDescription::Volume, TickDescription::AskVolume, TickDescription::BidVolume], "'v', 'A' or 'B'"),
+            _ => (&[], "")
+        };
+        if !needs.is_empty() && !needs.iter().any(|desc| tick.contains(desc)) {
+            panic!("The {} column needs a volume column in --tick ({})", column.name(), codes);
+        }
+    }
+    columns
+}
+
+/// The columns of the input bars with --bars. Sides must have all of their
+/// open, high, low and close columns.
+pub fn bar_columns(matches: &ArgMatches) -> Option<Vec<Column>> {
+    let template = matches.value_of("bars")?;
+    let columns = template::parse(template).unwrap_or_else(|e| panic!("--bars contains {}", e));
+    if !columns.contains(&Column::DateTime) {
+        panic!("--bars needs a time column: '{}'", template);
+    }
+    let aggregates = [Aggregate::Open, Aggregate::High, Aggregate::Low, Aggregate::Close];
+    let mut sides = 0;
+    for side in &[AskBid::Ask, AskBid::Bid, AskBid::Mid] {
+        let count = aggregates.iter().filter(|aggregate| columns.contains(&Column::Price(*side, **aggregate))).count();
+        if count == aggregates.len() {
+            sides += 1;
+        } else if count > 0 {
+            let name = Column::Price(*side, Aggregate::Open).header().0;
+            panic!("--bars needs the open, high, low and close columns of the {} prices: '{}'", name, template);
+        }
+    }
+    if sides == 0 {
+        panic!("--bars needs the open, high, low and close columns of the ask, bid or mid prices: '{}'", template);
+    }
+    for column in &columns {
+        match *column {
+            Column::Price(_, Aggregate::Statistic(_)) | Column::Extra(ExtraColumn::SpreadMin) | Column::Extra(ExtraColumn::SpreadMax) |
+            Column::Extra(ExtraColumn::SpreadMean) | Column::Extra(ExtraColumn::SpreadClose) =>
+                panic!("--bars cannot read the {} column, it cannot be resampled", column.name()),
+            _ => {}
+        }
+    }
+    Some(columns)
+}
+
+/// The volume, spread and tick count columns written after the prices
+pub fn extra_columns(matches: &ArgMatches) -> Vec<ExtraColumn> {
+    let mut columns: Vec<ExtraColumn> = Vec::new();
+    if matches.is_present("volume") {
+        columns.push(ExtraColumn::Volume);
+    }
+    if matches.is_present("ask-bid-volume") {
+        columns.push(ExtraColumn::AskVolume);
+        columns.push(ExtraColumn::BidVolume);
+    }
+    if matches.is_present("spread") {
+        columns.push(ExtraColumn::SpreadMin);
+        columns.push(ExtraColumn::SpreadMax);
+        columns.push(ExtraColumn::SpreadMean);
+        columns.push(ExtraColumn::SpreadClose);
+    }
+    if matches.is_present("tick-count") {
+        columns.push(ExtraColumn::TickCount);
+    }
+    columns
+}
+
+/// The pip size spreads are written in, from --pip-size or the default of
+/// --symbol. Without --spread-pips spreads are in price units.
+pub fn spread_pip_size(matches: &ArgMatches) -> Option<Decimal> {
+    if !matches.is_present("spread-pips") {
+        return None;
+    }
+    if let Some(pip_size) = matches.value_of("pip-size") {
+        return match Decimal::from_str(pip_size) {
+            Ok(pip_size) if pip_size > Decimal::zero() => Some(pip_size),
+            _ => panic!("--pip-size must be a positive number: '{}'", pip_size)
+        };
+    }
+    match matches.value_of("symbol") {
+        Some(symbol) => match Precision::pip_size(symbol) {
+            Some(pip_size) => Some(pip_size),
+            None => panic!("No default pip size for symbol '{}', use --pip-size", symbol)
+        },
+        None => panic!("--spread-pips needs --pip-size or --symbol")
+    }
+}
+
+/// When bars close, from --bar-type "time" or "ticks:N". Tick bars cannot be
+/// made from --bars.
+pub fn bar_type(matches: &ArgMatches) -> BarType {
+    let bar_type = matches.value_of("bar-type").unwrap_or("time");
+    if bar_type == "time" {
+        return BarType::Time;
+    }
+    match bar_type.strip_prefix("ticks:").and_then(|count| count.parse::<usize>().ok()) {
+        Some(count) if count > 0 => {
+            if matches.is_present("bars") {
+                panic!("--bar-type {} cannot be used with --bars", bar_type);
+            }
+            BarType::Ticks(count)
+        },
+        _ => panic!("--bar-type must be time or ticks:N with N above zero: '{}'", bar_type)
+    }
+}
+
+pub fn fill(matches: &ArgMatches) -> Fill {
+    match matches.value_of("fill") {
+        Some("forward") => Fill::Forward,
+        Some("nan") => Fill::NaN,
+        _ => Fill::None
+    }
+}
+
+/// The trading session with --session, the FX session unless its hours, zone
+/// or holidays are given
+pub fn session(matches: &ArgMatches) -> Option<(Session, OutOfSession)> {
+    if !matches.is_present("session") {
+        return None;
+    }
+    let mut session = Session::fx();
+    if matches.is_present("session-tz") {
+        session = session.with_zone(zone(matches, "session-tz"));
+    }
+    if let Some(open) = matches.value_of("session-open") {
+        let (weekday, time) = session::parse_weekday_time(open).unwrap_or_else(|| panic!("--session-open must be a weekday and time (eg. \"Sun 17:00\"): '{}'", open));
+        session = session.with_open(weekday, time);
+    }
+    if let Some(close) = matches.value_of("session-close") {
+        let (weekday, time) = session::parse_weekday_time(close).unwrap_or_else(|| panic!("--session-close must be a weekday and time (eg. \"Fri 17:00\"): '{}'", close));
+        session = session.with_close(weekday, time);
+    }
+    if let Some(name) = matches.value_of("holidays") {
+        session = session.with_holidays(holidays(name));
+    }
+    let out_of_session = match matches.value_of("out-of-session") {
+        Some("warn") => OutOfSession::Warn,
+        _ => OutOfSession::Exclude
+    };
+    Some((session, out_of_session))
+}
+
+/// Reads the holidays file, a date (eg. 2016-12-26) on each line. Empty lines
+/// and lines starting with '#' are skipped.
+fn holidays(name: &str) -> Vec<NaiveDate> {
+    let mut file = File::open(name).unwrap_or_else(|_| panic!("Could not open holidays file '{}'", name));
+    let mut text = String::new();
+    file.read_to_string(&mut text).unwrap_or_else(|_| panic!("Could not read holidays file '{}'", name));
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match NaiveDate::parse_from_str(line, "%Y-%m-%d") {
+            Ok(date) => dates.push(date),
+            Err(_) => panic!("Line {} of holidays file '{}', invalid date: '{}'", index + 1, name, line)
+        }
+    }
+    dates
+}
+
 pub fn headers(matches: &ArgMatches) -> bool{
     matches.is_present("headers")
 }
@@ -128,6 +496,93 @@ pub fn bar(matches: &ArgMatches) -> bool{
     matches.is_present("bar")
 }
 
+pub fn tick_format(matches: &ArgMatches) -> TickFormat {
+    TickFormat {
+        tick: tick(matches),
+        datetime: datetime_format(matches),
+        zone: input_zone(matches),
+        delimiter: in_delimiter(matches),
+        header: input_header(matches),
+        columns: column_names(matches),
+        bars: bar_columns(matches)
+    }
+}
+
+pub fn input_header(matches: &ArgMatches) -> Header {
+    if matches.is_present("input-header") {
+        Header::Present
+    } else {
+        Header::Auto
+    }
+}
+
+pub fn column_names(matches: &ArgMatches) -> Option<ColumnNames> {
+    match (matches.value_of("datetime-col"), matches.value_of("ask-col"), matches.value_of("bid-col")) {
+        (Some(datetime), Some(ask), Some(bid)) => Some(ColumnNames {
+            datetime: String::from(datetime),
+            ask: String::from(ask),
+            bid: String::from(bid)
+        }),
+        _ => None
+    }
+}
+
+fn delimiter(matches: &ArgMatches, name: &str) -> Delimiter {
+    let delimiter = matches.value_of(name).unwrap_or(",");
+    match delimiter {
+        "tab" | "\\t" => Delimiter::Char('\t'),
+        "space" => Delimiter::Char(' '),
+        "whitespace" => Delimiter::Whitespace,
+        _ => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Delimiter::Char(c),
+                _ => panic!("--{} must be a single character, tab, space or whitespace: '{}'", name, delimiter)
+            }
+        }
+    }
+}
+
+pub fn in_delimiter(matches: &ArgMatches) -> Delimiter {
+    delimiter(matches, "in-delim")
+}
+
+pub fn out_delimiter(matches: &ArgMatches) -> Delimiter {
+    delimiter(matches, "out-delim")
+}
+
+fn zone(matches: &ArgMatches, name: &str) -> Zone {
+    match matches.value_of(name) {
+        Some(zone) => match Zone::parse(zone) {
+            Some(zone) => zone,
+            None => panic!("--{} is not a valid time zone: '{}'", name, zone)
+        },
+        None => Zone::utc()
+    }
+}
+
+pub fn input_zone(matches: &ArgMatches) -> Zone {
+    zone(matches, "input-tz")
+}
+
+pub fn output_zone(matches: &ArgMatches) -> Zone {
+    zone(matches, "output-tz")
+}
+
+pub fn datetime_format(matches: &ArgMatches) -> DateTimeFormat {
+    if let Some(pattern) = matches.value_of("datetime-format") {
+        return DateTimeFormat::Pattern(String::from(pattern));
+    }
+    match matches.value_of("epoch") {
+        Some("s") => DateTimeFormat::Epoch(EpochUnit::Seconds),
+        Some("ms") => DateTimeFormat::Epoch(EpochUnit::Milliseconds),
+        Some("us") => DateTimeFormat::Epoch(EpochUnit::Microseconds),
+        Some("ns") => DateTimeFormat::Epoch(EpochUnit::Nanoseconds),
+        Some(_) => DateTimeFormat::Epoch(EpochUnit::Auto),
+        None => DateTimeFormat::Fixed
+    }
+}
+
 pub fn tick(matches: &ArgMatches) -> Vec<TickDescription> {
     let mut description: Vec<TickDescription> = Vec::new();
     if let Some(tick) = matches.value_of("tick") {
@@ -151,6 +606,24 @@ pub fn tick(matches: &ArgMatches) -> Vec<TickDescription> {
                     }
                     description.push(TickDescription::Bid);
                 },
+                'v' => {
+                    if description.contains(&TickDescription::Volume) {
+                        panic!("--tick option contains duplicat 'v' values");
+                    }
+                    description.push(TickDescription::Volume);
+                },
+                'A' => {
+                    if description.contains(&TickDescription::AskVolume) {
+                        panic!("--tick option contains duplicat 'A' values");
+                    }
+                    description.push(TickDescription::AskVolume);
+                },
+                'B' => {
+                    if description.contains(&TickDescription::BidVolume) {
+                        panic!("--tick option contains duplicat 'B' values");
+                    }
+                    description.push(TickDescription::BidVolume);
+                },
                 'x' => {
                     description.push(TickDescription::Filler);
                 },
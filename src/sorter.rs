@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{BufReader, SeekFrom};
+use chrono::prelude::*;
+use formatter::{parse, ParseError, TickFormat};
+use input::Input;
+
+/// The first and last tick datetimes of an input file
+type Range = Option<(DateTime<Utc>, DateTime<Utc>)>;
+
+/// Orders the input files by their first tick and checks that their time
+/// ranges do not overlap. Overlaps are refused unless they are allowed, in
+/// which case they are only reported. A single input is not read at all.
+pub fn sort(inputs: Vec<Input>, format: &TickFormat, allow_overlap: bool) -> Vec<Input> {
+    if inputs.len() < 2 {
+        return inputs;
+    }
+    let mut inputs: Vec<(Input, TickFormat, Option<DateTime<Utc>>)> = inputs.into_iter().map(|input| {
+        let (format, first) = first_tick(&input, format);
+        (input, format, first)
+    }).collect();
+    // files without ticks are placed first, they do not affect the output
+    inputs.sort_by_key(|&(_, _, first)| first);
+
+    // no file starts after the last one, so it is not read to its end
+    let count = inputs.len();
+    let ranges: Vec<Range> = inputs.iter().enumerate().map(|(index, &(ref input, ref format, first))| {
+        first.map(|first| if index + 1 < count { (first, last_tick(input, format).unwrap_or(first)) } else { (first, first) })
+    }).collect();
+    for (earlier, later) in overlaps(&ranges) {
+        let msg = format!("Input files overlap: '{}' ends at {} after '{}' starts at {}",
+            inputs[earlier].0.name, ranges[earlier].unwrap().1, inputs[later].0.name, ranges[later].unwrap().0);
+        if allow_overlap {
+            eprintln!("Warning: {}", msg);
+        } else {
+            panic!("{} (use --allow-overlap to convert anyway)", msg);
+        }
+    }
+    inputs.into_iter().map(|(input, _, _)| input).collect()
+}
+
+/// Finds the files that start before an earlier file has ended. The ranges
+/// must be sorted by their first datetime. Pairs are (earlier, later) indexes.
+fn overlaps(ranges: &[Range]) -> Vec<(usize, usize)> {
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    // the file that ends the latest so far
+    let mut latest: Option<(usize, DateTime<Utc>)> = None;
+    for (index, range) in ranges.iter().enumerate() {
+        if let Some((first, last)) = *range {
+            if let Some((earlier, end)) = latest {
+                if first < end {
+                    pairs.push((earlier, index));
+                }
+            }
+            match latest {
+                Some((_, end)) if end >= last => {},
+                _ => latest = Some((index, last))
+            }
+        }
+    }
+    pairs
+}
+
+/// Peeks at the first tick of the input, only its first lines are read. The
+/// format is returned with the columns named by the header, if any.
+fn first_tick(input: &Input, format: &TickFormat) -> (TickFormat, Option<DateTime<Utc>>) {
+    // the first lines may be headers, so the first tick could be after them
+    let head = input.read(|reader| first_lines(reader, format.header_lines() + 1));
+    let mut head = head.into_iter();
+    let mut format = format.clone();
+    let mut first = head.next();
+    for _ in 0..format.header_lines() {
+        match first {
+            Some(ref line) if format.is_header(line) => {
+                format = format.with_header(line).unwrap_or_else(|e| panic!("Header line in '{}', {}", input.name, e));
+            },
+            _ => break
+        }
+        first = head.next();
+    }
+    let first = first.map(|line| peek(&input.name, "First", &line, &format));
+    (format, first)
+}
+
+/// Peeks at the last tick of the input. Compressed inputs cannot be read from
+/// the end so they are read through in full.
+fn last_tick(input: &Input, format: &TickFormat) -> Option<DateTime<Utc>> {
+    let last = if input.is_plain() {
+        last_line(&mut input.file())
+    } else {
+        input.read(|reader| read_last_line(reader))
+    };
+    last.map(|line| peek(&input.name, "Last", &line, format))
+}
+
+fn peek(name: &str, position: &str, line: &str, format: &TickFormat) -> DateTime<Utc> {
+    match parse(line, format) {
+        Ok(row) => row.datetime,
+        Err(ParseError::InvalidLine) => panic!("Invalid {} line in '{}': '{}'", position.to_lowercase(), name, line),
+        Err(ParseError::Column(e)) => panic!("{} line in '{}', {}", position, name, e)
+    }
+}
+
+/// The first lines of the input that are not empty. Bytes that are not UTF-8
+/// are replaced, they are reported with their line number when the input is
+/// converted.
+fn first_lines<R: Read>(reader: R, count: usize) -> Vec<String> {
+    let mut reader = BufReader::new(reader);
+    let mut lines: Vec<String> = Vec::new();
+    let mut bytes: Vec<u8> = Vec::new();
+    while lines.len() < count {
+        bytes.clear();
+        if reader.read_until(b'\n', &mut bytes).expect("Could not read input file") == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&bytes);
+        if !line.trim().is_empty() {
+            lines.push(String::from(line.trim()));
+        }
+    }
+    lines
+}
+
+/// The last line of the input that is not empty, read through in full
+fn read_last_line<R: Read>(reader: R) -> Option<String> {
+    let mut reader = BufReader::new(reader);
+    let mut last: Option<String> = None;
+    let mut bytes: Vec<u8> = Vec::new();
+    loop {
+        bytes.clear();
+        if reader.read_until(b'\n', &mut bytes).expect("Could not read input file") == 0 {
+            return last;
+        }
+        let line = String::from_utf8_lossy(&bytes);
+        if !line.trim().is_empty() {
+            last = Some(String::from(line.trim()));
+        }
+    }
+}
+
+/// The last line of the file that is not empty, read backwards from the end
+/// of the file so that large files are not read in full
+fn last_line(file: &mut File) -> Option<String> {
+    let size = file.seek(SeekFrom::End(0)).expect("Could not read input file");
+    let mut chunk: u64 = 4096;
+    loop {
+        let start = size.saturating_sub(chunk);
+        let mut bytes: Vec<u8> = Vec::new();
+        file.seek(SeekFrom::Start(start)).expect("Could not read input file");
+        file.read_to_end(&mut bytes).expect("Could not read input file");
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_end();
+        // the line is only complete once the newline before it is found
+        if let Some(pos) = text.rfind('\n') {
+            return Some(String::from(text[pos + 1..].trim()));
+        }
+        if start == 0 {
+            return if text.trim().is_empty() { None } else { Some(String::from(text.trim())) };
+        }
+        chunk *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gen_range(first: u32, last: u32) -> Range {
+        Some((Utc.ymd(2016, 11, first).and_hms(0, 0, 0), Utc.ymd(2016, 11, last).and_hms(23, 59, 59)))
+    }
+
+    #[test]
+    fn first_and_last() {
+        let input = "\nAUD/USD,20161101 22:30:03.617,0.76541,0.76551\nAUD/USD,20161101 22:30:45.102,0.76558,0.76559\n\n";
+        assert_eq!(first_lines(input.as_bytes(), 1), vec![String::from("AUD/USD,20161101 22:30:03.617,0.76541,0.76551")]);
+        assert_eq!(read_last_line(input.as_bytes()), Some(String::from("AUD/USD,20161101 22:30:45.102,0.76558,0.76559")));
+        assert_eq!(first_lines("\n \n".as_bytes(), 2), Vec::<String>::new());
+        assert_eq!(read_last_line("\n \n".as_bytes()), None);
+        assert_eq!(first_lines(&b"a,\xff\nb,2\n"[..], 2), vec![String::from("a,\u{fffd}"), String::from("b,2")]);
+        assert_eq!(read_last_line(&b"a,1\nb,\xff\n"[..]), Some(String::from("b,\u{fffd}")));
+    }
+
+    #[test]
+    fn no_overlaps() {
+        assert_eq!(overlaps(&[None, gen_range(1, 1), gen_range(2, 3), gen_range(4, 4)]), vec![]);
+    }
+
+    #[test]
+    fn overlap_with_previous() {
+        assert_eq!(overlaps(&[gen_range(1, 2), gen_range(2, 3)]), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn overlap_with_earlier() {
+        assert_eq!(overlaps(&[gen_range(1, 9), gen_range(2, 3), gen_range(4, 5), gen_range(10, 11)]), vec![(0, 1), (0, 2)]);
+    }
+}
@@ -0,0 +1,157 @@
+use fxconv::AskBid;
+use fxconv::ExtraColumn;
+use fxconv::Statistic;
+
+/// How the prices of a side are aggregated into a column
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum Aggregate {
+    Open,
+    High,
+    Low,
+    Close,
+    Statistic(Statistic)
+}
+
+/// A column of the output
+#[derive(Debug)]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+pub enum Column {
+    DateTime,
+    Price(AskBid, Aggregate),
+    Extra(ExtraColumn)
+}
+
+impl Column {
+    /// The name of the column in a template, eg. "ask.open"
+    pub fn name(&self) -> String {
+        let (group, field) = match *self {
+            Column::DateTime => ("", "time"),
+            _ => self.header()
+        };
+        if group.is_empty() {
+            String::from(field)
+        } else {
+            format!("{}.{}", group, field)
+        }
+    }
+
+    /// The two header lines of the column, the group and the field
+    pub fn header(&self) -> (&'static str, &'static str) {
+        match *self {
+            Column::DateTime => ("", "datetime"),
+            Column::Price(side, aggregate) => {
+                let group = match side {
+                    AskBid::Ask => "ask",
+                    AskBid::Bid => "bid",
+                    AskBid::Mid => "mid"
+                };
+                let field = match aggregate {
+                    Aggregate::Open => "open",
+                    Aggregate::High => "high",
+                    Aggregate::Low => "low",
+                    Aggregate::Close => "close",
+                    Aggregate::Statistic(Statistic::Twap) => "twap",
+                    Aggregate::Statistic(Statistic::Vwap) => "vwap",
+                    Aggregate::Statistic(Statistic::Mean) => "mean",
+                    Aggregate::Statistic(Statistic::Median) => "median",
+                    Aggregate::Statistic(Statistic::StdDev) => "stddev"
+                };
+                (group, field)
+            },
+            Column::Extra(extra) => match extra {
+                ExtraColumn::Volume => ("", "volume"),
+                ExtraColumn::AskVolume => ("ask", "volume"),
+                ExtraColumn::BidVolume => ("bid", "volume"),
+                ExtraColumn::SpreadMin => ("spread", "min"),
+                ExtraColumn::SpreadMax => ("spread", "max"),
+                ExtraColumn::SpreadMean => ("spread", "mean"),
+                ExtraColumn::SpreadClose => ("spread", "close"),
+                ExtraColumn::TickCount => ("", "ticks")
+            }
+        }
+    }
+}
+
+/// Every column that can be written, in template order
+fn all() -> Vec<Column> {
+    let mut columns = vec![Column::DateTime];
+    for side in &[AskBid::Ask, AskBid::Bid, AskBid::Mid] {
+        for aggregate in &[Aggregate::Open, Aggregate::High, Aggregate::Low, Aggregate::Close] {
+            columns.push(Column::Price(*side, *aggregate));
+        }
+        for statistic in &[Statistic::Twap, Statistic::Vwap, Statistic::Mean, Statistic::Median, Statistic::StdDev] {
+            columns.push(Column::Price(*side, Aggregate::Statistic(*statistic)));
+        }
+    }
+    for extra in &[ExtraColumn::Volume, ExtraColumn::AskVolume, ExtraColumn::BidVolume, ExtraColumn::SpreadMin,
+        ExtraColumn::SpreadMax, ExtraColumn::SpreadMean, ExtraColumn::SpreadClose, ExtraColumn::TickCount] {
+        columns.push(Column::Extra(*extra));
+    }
+    columns
+}
+
+/// Reads a template of comma separated column names, eg.
+/// "time,bid.open,bid.close,ask.high,ticks,spread.mean"
+pub fn parse(template: &str) -> Result<Vec<Column>, String> {
+    let all = all();
+    let mut columns: Vec<Column> = Vec::new();
+    for name in template.split(',') {
+        let name = name.trim().to_lowercase();
+        let name = if name == "datetime" { String::from("time") } else { name };
+        match all.iter().find(|column| column.name() == name) {
+            Some(column) => columns.push(*column),
+            None => return Err(format!("unknown column '{}'", name))
+        }
+    }
+    Ok(columns)
+}
+
+/// The template of the datetime, the OHLC columns and statistics of each
+/// side followed by the extra columns
+pub fn shorthand(sides: &[AskBid], statistics: &[(AskBid, Statistic)], extra_columns: &[ExtraColumn]) -> Vec<Column> {
+    let mut columns = vec![Column::DateTime];
+    for side in sides {
+        for aggregate in &[Aggregate::Open, Aggregate::High, Aggregate::Low, Aggregate::Close] {
+            columns.push(Column::Price(*side, *aggregate));
+        }
+        for &(_, statistic) in statistics.iter().filter(|(s, _)| s == side) {
+            columns.push(Column::Price(*side, Aggregate::Statistic(statistic)));
+        }
+    }
+    for extra in extra_columns {
+        columns.push(Column::Extra(*extra));
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for column in all() {
+            assert_eq!(parse(&column.name()), Ok(vec![column]));
+        }
+        assert_eq!(parse("time,bid.open,bid.close,ask.high,ticks,spread.mean"), Ok(vec![
+            Column::DateTime,
+            Column::Price(AskBid::Bid, Aggregate::Open),
+            Column::Price(AskBid::Bid, Aggregate::Close),
+            Column::Price(AskBid::Ask, Aggregate::High),
+            Column::Extra(ExtraColumn::TickCount),
+            Column::Extra(ExtraColumn::SpreadMean)
+        ]));
+        assert_eq!(parse("DateTime, Mid.VWAP"), Ok(vec![Column::DateTime, Column::Price(AskBid::Mid, Aggregate::Statistic(Statistic::Vwap))]));
+        assert_eq!(parse("time,ask.average"), Err(String::from("unknown column 'ask.average'")));
+    }
+
+    #[test]
+    fn shorthands() {
+        let columns = shorthand(&[AskBid::Bid], &[(AskBid::Bid, Statistic::Mean)], &[ExtraColumn::TickCount]);
+        let names: Vec<String> = columns.iter().map(|column| column.name()).collect();
+        assert_eq!(names, vec!["time", "bid.open", "bid.high", "bid.low", "bid.close", "bid.mean", "ticks"]);
+    }
+}
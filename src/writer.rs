@@ -0,0 +1,38 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::sync::mpsc::Receiver;
+use std::thread;
+use converter::Row;
+use market::timezone::Zone;
+use fxconv::Delimiter;
+use template::Column;
+
+/// Writes the converted rows to the output file in the order of the column
+/// template, datetimes in the zone
+pub fn create(rx_converter: Receiver<Option<Row>>, output_file: File, zone: Zone, delimiter: Delimiter, columns: Vec<Column>) -> thread::JoinHandle<()> {
+    let t = thread::Builder::new().name("writer".to_string()).spawn(move || {
+        writer(rx_converter, output_file, zone, delimiter, columns);
+    });
+    t.expect("Thread did not spawn correctly")
+}
+
+fn writer(rx_converter: Receiver<Option<Row>>, mut output_file: File, zone: Zone, delimiter: Delimiter, columns: Vec<Column>) {
+    let separator = delimiter.separator();
+    while let Some(row) = rx_converter.recv().expect("Unable to receive from channel") {
+        let mut line: Vec<String> = Vec::new();
+        let mut values = row.column_data.iter();
+        for column in &columns {
+            match *column {
+                Column::DateTime => line.push(zone.display(row.datetime)),
+                _ => match *values.next().expect("Row does not match the column template") {
+                    Some(value) => line.push(value.to_string()),
+                    None => line.push(String::from("NaN"))
+                }
+            }
+        }
+        let line = line.join(&separator);
+        let line = line.as_bytes();
+        output_file.write_all(line).expect("Could not write to file");
+        output_file.write_all(b"\n").expect("Could not write to file");
+    }
+}
@@ -40,3 +40,56 @@ t()
 
 t "Simple test" 1m out00.csv "in00.csv"
 t "gaps" 1s out01.csv "in00.csv"
+t "continues across files" 1m out00.csv "in02a.csv in02b.csv"
+t "orders inputs" 1m out00.csv "in02b.csv in02a.csv"
+t "compressed inputs" 1m out00.csv "in03a.csv.gz in03b.csv.xz"
+t "zip archive" 1m out00.csv "in03.zip"
+t "datetime format" "1m --datetime-format '%Y.%m.%d %H:%M:%S%.f'" out00.csv "in04.csv"
+t "sub-second timeframe" 250ms out01.csv "in00.csv"
+t "epoch datetimes" "1m --epoch auto" out00.csv "in05.csv"
+t "time zones" "1m --input-tz +02:00 --output-tz +02:00" out02.csv "in00.csv"
+t "delimiters" "1m --in-delim tab --out-delim \";\"" out03.csv "in06.tsv"
+t "columns by name" "1m --datetime-col timestamp --ask-col ask --bid-col bid" out00.csv "in07.csv"
+t "output digits" "1m --digits 6" out04.csv "in00.csv"
+t "volume and tick count" "1m -t xdabAB --volume --ask-bid-volume --tick-count" out05.csv "in08.csv"
+t "spread in pips" "1m --spread --spread-pips --symbol AUDUSD --digits 5 -h" out06.csv "in00.csv"
+t "mid prices" "1m --mid -h" out07.csv "in00.csv"
+t "statistics" "1m -t xdabAB --mid --stats twap,vwap,mid:median,mid:stddev --digits 5 -h" out08.csv "in08.csv"
+t "column template" "1m -t xdabAB --columns time,bid.open,bid.close,ask.high,ticks,spread.mean,volume -h" out09.csv "in08.csv"
+t "forward fill" "1s --fill forward --tick-count --label open" out10.csv "in00.csv"
+t "nan fill" "1s --fill nan --tick-count --bid-only --label open" out11.csv "in00.csv"
+t "trading session" "1d --session --label open" out12.csv "in09.csv"
+t "resampled bars" "5m --bars time,ask.open,ask.high,ask.low,ask.close,bid.open,bid.high,bid.low,bid.close,volume,ticks --datetime-format '%Y-%m-%d %H:%M:%S UTC' --label open --volume --tick-count" out13.csv "in10.csv"
+t "tick bars" "1d --bar-type ticks:2 --tick-count --label open" out14.csv "in00.csv"
+
+# several timeframes are written to the files named by the output template
+rm -rf out.1m.temp out.1s.temp
+if $exe 1m,1s "out.{timeframe}.temp" in00.csv > stdout$COUNT && diff out00.csv out.1m.temp && diff out01.csv out.1s.temp; then
+  echo "Test $COUNT ${green}[OK]${reset}: fxconv 1m,1s out.{timeframe}.temp in00.csv"
+else
+  echo "Test $COUNT ${red}[FAIL]${reset}: fxconv 1m,1s out.{timeframe}.temp in00.csv"
+  exit 1
+fi
+rm -rf out.1m.temp out.1s.temp stdout$COUNT
+COUNT=`expr $COUNT + 1`
+
+# overlapping inputs are refused before the output file is written
+echo "kept" > out.temp
+if ! $exe 1m --overwrite out.temp in00.csv in02b.csv > stdout$COUNT 2>&1 && grep -q "Input files overlap" stdout$COUNT && [ "`cat out.temp`" = "kept" ]; then
+  echo "Test $COUNT ${green}[OK]${reset}: fxconv 1m --overwrite out.temp in00.csv in02b.csv"
+else
+  echo "Test $COUNT ${red}[FAIL]${reset}: fxconv 1m --overwrite out.temp in00.csv in02b.csv"
+  exit 1
+fi
+rm -rf out.temp stdout$COUNT
+COUNT=`expr $COUNT + 1`
+
+# ticks outside of the session are reported once for all the timeframes
+rm -rf out.1d.temp out.1h.temp
+if $exe 1d,1h --session --out-of-session warn --label open "out.{timeframe}.temp" in09.csv > stdout$COUNT 2>&1 && diff out15.csv out.1d.temp && diff out16.csv out.1h.temp && [ `grep -c "outside of the trading session" stdout$COUNT` = 1 ]; then
+  echo "Test $COUNT ${green}[OK]${reset}: fxconv 1d,1h --session --out-of-session warn --label open out.{timeframe}.temp in09.csv"
+else
+  echo "Test $COUNT ${red}[FAIL]${reset}: fxconv 1d,1h --session --out-of-session warn --label open out.{timeframe}.temp in09.csv"
+  exit 1
+fi
+rm -rf out.1d.temp out.1h.temp stdout$COUNT